@@ -0,0 +1,68 @@
+//! cross-thread throughput of the thread-safe ring buffers, one producer thread and one
+//! consumer thread hammering on `tail` and `head` respectively
+
+use std::sync::Arc;
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ringbuffer::{ringbuffer_ts, ringbuffer_ts_g};
+
+const COUNT: usize = 100_000;
+
+fn spsc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spsc");
+    group.throughput(Throughput::Elements(COUNT as u64));
+
+    group.bench_function("ringbuffer_ts push/pop", |b| {
+        b.iter(|| {
+            let consumer = Arc::new(ringbuffer_ts::RingBuffer::<1024>::new());
+            let producer = Arc::clone(&consumer);
+            let t = thread::spawn(move || {
+                for i in 0..COUNT {
+                    while producer.try_push(i).is_err() {
+                        thread::yield_now();
+                    }
+                }
+            });
+            let mut received = 0;
+            while received < COUNT {
+                match consumer.try_pop() {
+                    Some(_) => received += 1,
+                    None => thread::yield_now(),
+                }
+            }
+            t.join().unwrap();
+        })
+    });
+
+    group.bench_function("ringbuffer_ts_g write/read", |b| {
+        b.iter(|| {
+            let consumer = Arc::new(ringbuffer_ts_g::RingBuffer::<usize, 1024>::new());
+            let producer = Arc::clone(&consumer);
+            let t = thread::spawn(move || {
+                let data: Vec<usize> = (0..16).collect();
+                let mut sent = 0;
+                while sent < COUNT {
+                    match producer.n_write(&data) {
+                        0 => thread::yield_now(),
+                        n => sent += n,
+                    }
+                }
+            });
+            let mut output = Vec::with_capacity(1024);
+            let mut received = 0;
+            while received < COUNT {
+                match consumer.n_read(&mut output) {
+                    0 => thread::yield_now(),
+                    n => received += n,
+                }
+            }
+            t.join().unwrap();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, spsc);
+criterion_main!(benches);
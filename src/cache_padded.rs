@@ -0,0 +1,48 @@
+//! pads a value out to its own cache line, so values written by different threads
+//! don't keep invalidating each other's cache line (false sharing)
+
+use std::ops::Deref;
+
+
+#[repr(align(64))]
+pub(crate) struct CachePadded<T> {
+    value: T,
+}
+
+impl<T> CachePadded<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self { value }
+    }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::CachePadded;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn separate_cache_lines() {
+        struct Counters {
+            head: CachePadded<AtomicUsize>,
+            tail: CachePadded<AtomicUsize>,
+        }
+        let counters = Counters {
+            head: CachePadded::new(AtomicUsize::new(0)),
+            tail: CachePadded::new(AtomicUsize::new(0)),
+        };
+        let head = &*counters.head as *const AtomicUsize as usize;
+        let tail = &*counters.tail as *const AtomicUsize as usize;
+        assert_eq!(head % 64, 0);
+        assert_eq!(tail % 64, 0);
+        assert!(head.abs_diff(tail) >= 64);
+    }
+}
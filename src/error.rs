@@ -0,0 +1,56 @@
+//! the error returned by the `Result` based APIs of the ring buffers
+//! the older APIs keep signalling failure with a count of 0, an `Option` or the value handed back
+
+use core::fmt;
+use crate::ringbuffer::CapacityError;
+
+
+/// why an operation on a ring buffer failed, the same for every buffer in this crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingBufferError {
+    /// not enough free space right now, reading makes room
+    Full,
+    /// nothing buffered to read
+    Empty,
+    /// more elements than the buffer can ever hold, no amount of reading makes room
+    CapacityExceeded { requested: usize, capacity: usize },
+}
+
+impl fmt::Display for RingBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "ring buffer is full"),
+            Self::Empty => write!(f, "ring buffer is empty"),
+            Self::CapacityExceeded { requested, capacity } => {
+                write!(f, "cannot write {} elements into a ring buffer of capacity {}", requested, capacity)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RingBufferError {}
+
+impl From<CapacityError> for RingBufferError {
+    fn from(err: CapacityError) -> Self {
+        Self::CapacityExceeded { requested: err.requested, capacity: err.capacity }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::RingBufferError;
+    use crate::ringbuffer::CapacityError;
+
+    #[test]
+    fn display() {
+        assert_eq!(RingBufferError::Full.to_string(), "ring buffer is full");
+        assert_eq!(RingBufferError::Empty.to_string(), "ring buffer is empty");
+
+        // same message as the `CapacityError` it converts from
+        let err = CapacityError { requested: 5, capacity: 4 };
+        assert_eq!(RingBufferError::from(err), RingBufferError::CapacityExceeded { requested: 5, capacity: 4 });
+        assert_eq!(RingBufferError::from(err).to_string(), err.to_string());
+    }
+}
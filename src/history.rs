@@ -0,0 +1,74 @@
+//! the last `N` events, e.g. for a crash dump
+//! a thin wrapper around the generic ring buffer that always overwrites
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use crate::ringbuffer::RingBuffer;
+
+
+/// records never fail, once `N` events are recorded each new one evicts the oldest
+pub struct History<T, const N: usize> {
+    ringbuffer: RingBuffer<T, N>,
+}
+
+impl<T, const N: usize> History<T, N>
+where
+    T: Copy
+{
+    pub fn new() -> Self {
+        Self { ringbuffer: RingBuffer::new() }
+    }
+
+    /// record `elem` as the newest event, evicting the oldest one when full
+    pub fn record(&mut self, elem: T) {
+        self.ringbuffer.extend([elem]);
+    }
+    /// the recorded events oldest first, at most the last `N`, without clearing them
+    #[cfg(feature = "alloc")]
+    pub fn dump(&self) -> Vec<T> {
+        self.ringbuffer.to_vec()
+    }
+
+    /// number of events currently held, at most `N`
+    pub fn len(&self) -> usize {
+        self.ringbuffer.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ringbuffer.is_empty()
+    }
+    /// total number of events recorded and since evicted
+    pub fn evicted(&self) -> usize {
+        self.ringbuffer.dropped_count()
+    }
+}
+
+impl<T: Copy, const N: usize> Default for History<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::History;
+
+    #[test]
+    fn last_n_events() {
+        let mut history = History::<u32, 8>::new();
+        assert!(history.dump().is_empty());
+
+        history.record(1);
+        history.record(2);
+        assert_eq!(history.dump(), vec![1, 2]);
+
+        for event in 3..=100 {
+            history.record(event);
+        }
+        assert_eq!(history.len(), 8);
+        assert_eq!(history.evicted(), 92);
+        assert_eq!(history.dump(), (93..=100).collect::<Vec<_>>());
+        // dumping doesn't consume
+        assert_eq!(history.dump(), (93..=100).collect::<Vec<_>>());
+    }
+}
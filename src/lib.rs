@@ -1,9 +1,124 @@
-#![feature(atomic_from_mut)]
+//! without the default `std` feature the crate is `no_std`, leaving only the single threaded
+//! ring buffers with their slice based API, `alloc` adds back the `Vec` based one
 
-mod stack;
-mod queue;
-mod queue_ts_g;
-mod ringbuffer;
-mod ringbuffer_ts;
-mod ringbuffer_ts_g;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+mod cache_padded;
+
+pub mod error;
+pub mod history;
+#[cfg(feature = "std")]
+pub mod stack;
+#[cfg(feature = "std")]
+pub mod queue;
+#[cfg(feature = "std")]
+pub mod queue_ts_g;
+pub mod ringbuffer;
+pub mod ringbuffer_clone;
+#[cfg(feature = "alloc")]
+pub mod ringbuffer_dyn;
+#[cfg(feature = "std")]
+pub mod ringbuffer_mpsc;
+pub mod ringbuffer_pow2;
+#[cfg(feature = "std")]
+pub mod ringbuffer_ts;
+#[cfg(feature = "std")]
+pub mod ringbuffer_ts_g;
+
+
+// which buffers can be shared between threads, checked at compile time. The single threaded
+// ones are `Sync` only in the trivial sense that `&self` can't change them.
+// `ringbuffer_ts_g::RingBuffer` is written and read through `&self`, a flag per side makes a
+// second writer (or reader) wait for the first.
+const _: () = {
+    #[cfg(feature = "std")]
+    const fn assert_send<T: Send>() {}
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ringbuffer::RingBuffer<usize, 1>>();
+    assert_send_sync::<ringbuffer_clone::CloneRingBuffer<usize, 1>>();
+    assert_send_sync::<ringbuffer_pow2::RingBufferPow2<usize, 2>>();
+    #[cfg(feature = "alloc")]
+    assert_send_sync::<ringbuffer_dyn::DynRingBuffer<usize>>();
+    #[cfg(feature = "std")]
+    assert_send_sync::<ringbuffer_ts::RingBuffer<1>>();
+    #[cfg(feature = "std")]
+    assert_send_sync::<ringbuffer_ts::BlockingRingBuffer<1>>();
+    #[cfg(feature = "std")]
+    assert_send_sync::<ringbuffer_ts_g::RingBuffer<usize, 1>>();
+    #[cfg(feature = "std")]
+    assert_send::<ringbuffer_ts::Producer<1>>();
+    #[cfg(feature = "std")]
+    assert_send::<ringbuffer_ts::Consumer<1>>();
+    #[cfg(feature = "std")]
+    assert_send_sync::<ringbuffer_mpsc::MpscRingBuffer<1>>();
+    #[cfg(feature = "std")]
+    assert_send::<ringbuffer_mpsc::MpscProducer<1>>();
+    #[cfg(feature = "std")]
+    assert_send::<ringbuffer_mpsc::MpscConsumer<1>>();
+};
+
+/// the operations every ring buffer in this crate supports, so code can be written once
+/// against any of them. None of them overwrite: `write` stores what fits and `read` takes
+/// everything buffered, both return the number of elements moved.
+#[cfg(feature = "alloc")]
+pub trait RingBuffer<T> {
+    fn write(&mut self, data: &[T]) -> usize;
+    /// clears `data` before filling it
+    fn read(&mut self, data: &mut Vec<T>) -> usize;
+    fn len(&self) -> usize;
+    fn capacity(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+}
+
+
+#[cfg(all(test, not(loom), feature = "std"))]
+mod test {
+    use super::RingBuffer;
+
+    /// the same sequence of writes and reads against any implementation, which must keep
+    /// FIFO order and agree on how much fits
+    fn fifo<B: RingBuffer<usize>>(mut ringbuffer: B) {
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.capacity(), 8);
+        assert!(ringbuffer.is_empty());
+
+        assert_eq!(ringbuffer.write(&[0, 1, 2, 3, 4]), 5);
+        assert_eq!(ringbuffer.len(), 5);
+        assert_eq!(ringbuffer.read(&mut result), 5);
+        assert_eq!(result, [0, 1, 2, 3, 4]);
+
+        // wraps around the end, the last two don't fit
+        assert_eq!(ringbuffer.write(&[5, 6, 7, 8, 9, 10]), 6);
+        assert_eq!(ringbuffer.write(&[11, 12, 13, 14]), 2);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.write(&[15]), 0);
+        assert_eq!(ringbuffer.read(&mut result), 8);
+        assert_eq!(result, (5..=12).collect::<Vec<_>>());
+
+        assert_eq!(ringbuffer.read(&mut result), 0);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn implementations_agree() {
+        fifo(crate::ringbuffer::RingBuffer::<usize, 8>::new());
+        fifo(crate::ringbuffer_pow2::RingBufferPow2::<usize, 8>::new());
+        fifo(crate::ringbuffer_dyn::DynRingBuffer::<usize>::new(8));
+        fifo(crate::ringbuffer_clone::CloneRingBuffer::<usize, 8>::new());
+        fifo(crate::ringbuffer_ts::RingBuffer::<8>::new());
+        fifo(crate::ringbuffer_ts_g::RingBuffer::<usize, 8>::new());
+    }
+}
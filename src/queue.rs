@@ -1,34 +1,316 @@
-/// thread unsafe queue with Rc<RefCell>
-/// todo!
+//! thread unsafe queue with Rc<RefCell>
+//! a doubly linked list usable as a deque, elements can be pushed and popped at both ends
 
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::cell::RefCell;
+use std::collections::VecDeque;
 
 pub struct List<T> {
     head: Node<T>,
     tail: Node<T>,
+    len: usize,
 }
 
 type Node<T> = Option<Rc<RefCell<NodeContent<T>>>>;
+/// a node is owned by its predecessor (or `head`), the link back is weak so neighbours
+/// don't keep each other alive in a cycle
+type PrevNode<T> = Option<Weak<RefCell<NodeContent<T>>>>;
 
 struct NodeContent<T> {
     elem: T,
     next: Node<T>,
-    prev: Node<T>,
+    prev: PrevNode<T>,
 }
 
 impl<T> List<T> {
     pub fn new() -> Self {
-        List { head: None, tail: None }
+        List { head: None, tail: None, len: 0 }
+    }
+    pub fn push_back(&mut self, elem: T) {
+        let new_tail = NodeContent::new(elem);
+        match self.tail.take() {
+            Some(old_tail) => {
+                new_tail.borrow_mut().prev = Some(Rc::downgrade(&old_tail));
+                old_tail.borrow_mut().next = Some(Rc::clone(&new_tail));
+                self.tail = Some(new_tail);
+            }
+            // empty list, the new node is both ends
+            None => {
+                self.head = Some(Rc::clone(&new_tail));
+                self.tail = Some(new_tail);
+            }
+        }
+        self.len += 1;
+    }
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.head.take().map(|old_head| {
+            match old_head.borrow_mut().next.take() {
+                Some(new_head) => {
+                    new_head.borrow_mut().prev.take();
+                    self.head = Some(new_head);
+                }
+                // that was the only node, the tail points at it too
+                None => {
+                    self.tail.take();
+                }
+            }
+            self.len -= 1;
+            // every other link to the node is gone now, so it can be moved out
+            Rc::try_unwrap(old_head).ok().unwrap().into_inner().elem
+        })
+    }
+    pub fn push_front(&mut self, elem: T) {
+        let new_head = NodeContent::new(elem);
+        match self.head.take() {
+            Some(old_head) => {
+                new_head.borrow_mut().next = Some(Rc::clone(&old_head));
+                old_head.borrow_mut().prev = Some(Rc::downgrade(&new_head));
+                self.head = Some(new_head);
+            }
+            None => {
+                self.tail = Some(Rc::clone(&new_head));
+                self.head = Some(new_head);
+            }
+        }
+        self.len += 1;
+    }
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.tail.take().map(|old_tail| {
+            // the predecessor is still owned through its own predecessor or `head`
+            match old_tail.borrow_mut().prev.take().and_then(|prev| prev.upgrade()) {
+                Some(new_tail) => {
+                    new_tail.borrow_mut().next.take();
+                    self.tail = Some(new_tail);
+                }
+                None => {
+                    self.head.take();
+                }
+            }
+            self.len -= 1;
+            Rc::try_unwrap(old_tail).ok().unwrap().into_inner().elem
+        })
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// move the elements into a `VecDeque`, keeping their front to back order
+    pub fn into_vec_deque(self) -> VecDeque<T> {
+        self.into_iter().collect()
+    }
+    /// build a list holding the elements of `deque` in the same front to back order
+    pub fn from_vec_deque(deque: VecDeque<T>) -> Self {
+        let mut list = List::new();
+        for elem in deque {
+            list.push_back(elem);
+        }
+        list
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// pops the elements off from front to back
+pub struct IntoIter<T>(List<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_front()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+/// unlink the nodes one by one, dropping `head` would otherwise drop every node
+/// recursively through the `next` links and overflow the stack on a long list
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
     }
 }
 
 impl<T> NodeContent<T> {
     fn new(elem: T) -> Rc<RefCell<Self>> {
         Rc::new(RefCell::new(NodeContent {
-            elem: elem,
+            elem,
             prev: None,
             next: None,
         }))
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::List;
+    use std::collections::VecDeque;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// counts how many of its instances have been dropped
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn push_back_pop_front() {
+        let mut list = List::new();
+        assert_eq!(list.pop_front(), None);
+
+        list.push_back(1);
+        list.push_back(2);
+        list.push_back(3);
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+
+        // refill after the list got down to one element
+        list.push_back(4);
+        assert_eq!(list.pop_front(), Some(3));
+        assert_eq!(list.pop_front(), Some(4));
+        assert_eq!(list.pop_front(), None);
+
+        // and again from empty
+        list.push_back(5);
+        assert_eq!(list.pop_front(), Some(5));
+        assert_eq!(list.pop_front(), None);
+    }
+
+    #[test]
+    fn deque() {
+        let mut list = List::new();
+        assert_eq!(list.pop_back(), None);
+
+        // builds 5 3 1 2 4
+        list.push_back(1);
+        list.push_front(3);
+        list.push_back(2);
+        list.push_back(4);
+        list.push_front(5);
+        assert_eq!(list.pop_front(), Some(5));
+        assert_eq!(list.pop_back(), Some(4));
+
+        // empty it from alternating ends
+        assert_eq!(list.pop_front(), Some(3));
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.pop_front(), None);
+
+        // used purely as a stack from the front
+        list.push_front(6);
+        list.push_front(7);
+        assert_eq!(list.pop_front(), Some(7));
+        assert_eq!(list.pop_back(), Some(6));
+        assert_eq!(list.pop_back(), None);
+    }
+
+    #[test]
+    fn drop_frees_nodes() {
+        let dropped = Rc::new(Cell::new(0));
+        let mut list = List::new();
+        for i in 0..1000 {
+            if i % 2 == 0 {
+                list.push_back(DropCounter(Rc::clone(&dropped)));
+            } else {
+                list.push_front(DropCounter(Rc::clone(&dropped)));
+            }
+        }
+        drop(list.pop_front());
+        drop(list.pop_back());
+        assert_eq!(dropped.get(), 2);
+
+        drop(list);
+        assert_eq!(dropped.get(), 1000);
+        // nothing but this test holds the counter anymore
+        assert_eq!(Rc::strong_count(&dropped), 1);
+    }
+
+    #[test]
+    fn len() {
+        let mut list = List::new();
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+
+        list.push_back(1);
+        list.push_front(2);
+        list.push_back(3);
+        assert_eq!(list.len(), 3);
+        list.pop_front();
+        assert_eq!(list.len(), 2);
+        assert!(!list.is_empty());
+        list.pop_back();
+        list.pop_back();
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+
+        // popping an empty list leaves it empty
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.len(), 0);
+    }
+
+    #[test]
+    fn drop_longqueue() {
+        let mut list = List::new();
+        for i in 0..100000 {
+            list.push_back(i);
+        }
+        drop(list);
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut list = List::new();
+        list.push_back(2);
+        list.push_back(3);
+        list.push_front(1);
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![2, 3]);
+
+        assert_eq!(List::<i32>::new().into_iter().next(), None);
+    }
+
+    #[test]
+    fn vec_deque_round_trip() {
+        let deque: VecDeque<i32> = (1..=5).collect();
+        let mut list = List::from_vec_deque(deque.clone());
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_back(), Some(5));
+
+        list.push_front(1);
+        list.push_back(5);
+        assert_eq!(list.into_vec_deque(), deque);
+
+        assert!(List::<i32>::new().into_vec_deque().is_empty());
+    }
+
+}
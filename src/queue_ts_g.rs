@@ -1,4 +1,4 @@
-/// thread safe and generic queue
+//! thread safe and generic queue
 
 use std::{
     ptr,
@@ -134,7 +134,7 @@ impl<T> Drop for LinkedQueue<T> {
         let h = self.head.load(Ordering::SeqCst);
         unsafe {
             // drop `h`
-            Box::from_raw(h);
+            let _ = Box::from_raw(h);
         }
     }
 }
@@ -1,81 +1,911 @@
-/// generic ring buffer
-/// the type T must implement Copy trait
+//! generic ring buffer
+//! the type T must implement Copy trait
+//! single threaded: everything that changes the buffer takes `&mut self`, so the counters are
+//! plain integers. It can be moved to another thread, for a producer and a consumer on
+//! different threads use `ringbuffer_ts` or `ringbuffer_ts_g`
 
+use core::cmp::min;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::Sum;
+use core::mem::MaybeUninit;
+use core::ops::Index;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+use crate::error::RingBufferError;
 
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::cmp::min;
 
+/// returned by `write_checked` when asked to write more elements than the buffer can ever hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError {
+    pub requested: usize,
+    pub capacity: usize,
+}
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot write {} elements into a ring buffer of capacity {}", self.requested, self.capacity)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CapacityError {}
+
+
+/// the producer side of a ring buffer with its element type only, so it can be held as
+/// `Box<dyn PushSink<T>>` without naming the buffer type or its capacity
+pub trait PushSink<T> {
+    /// push a single element, handing it back when the buffer is full
+    fn try_push(&mut self, value: T) -> Result<(), T>;
+    /// number of elements that can be pushed before the buffer is full
+    fn free(&self) -> usize;
+}
 
+
+/// slots in the live region `head..head + used_count` (with wraparound) are always initialized,
+/// all other slots may be uninitialized and are never read
 pub struct RingBuffer<T,const N: usize> {
-    buffer: [T; N],
-    head: AtomicUsize,
-    tail: AtomicUsize,
-    used_count: AtomicUsize,
+    buffer: [MaybeUninit<T>; N],
+    head: usize,
+    tail: usize,
+    used_count: usize,
+    /// elements lost so far, evicted by an overwrite or left out of a short write
+    dropped: usize,
+    /// the slots last handed out by `reserve` as `(tail, len)`, they are initialized and
+    /// `commit` may publish them as long as `tail` hasn't moved since
+    reservation: (usize, usize),
 }
 
 impl<T, const N:usize> RingBuffer<T,N> 
 where
-    T: Copy + Default
+    T: Copy
 {  
+    /// a capacity of 0 fails to compile, there would be no slot to index
+    const NON_ZERO: () = assert!(N > 0, "capacity of RingBuffer must be non-zero");
+
     pub fn new() -> Self {
+        let () = Self::NON_ZERO;
         Self {
-            buffer: [T::default(); N],
-            head: AtomicUsize::new(0),
-            tail: AtomicUsize::new(0),
-            used_count: AtomicUsize::new(0),
+            buffer: [MaybeUninit::uninit(); N],
+            head: 0,
+            tail: 0,
+            used_count: 0,
+            dropped: 0,
+            reservation: (0, 0),
         }
     }
+    /// build a buffer pre-filled with the first `N` elements of `data`,
+    /// any elements beyond the capacity are ignored
+    pub fn from_slice(data: &[T]) -> Self {
+        let mut ringbuffer = Self::new();
+        ringbuffer.write_from(data);
+        ringbuffer
+    }
 
-    fn n_write(&mut self, data: &Vec<T>) -> usize {
-        if N - self.used_count.load(Ordering::Relaxed) == 0 {
-            println!("buffer full");
+    /// write as many elements of `data` as there is free space for,
+    /// returns the number of elements actually written
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::ptr_arg)]
+    pub fn n_write(&mut self, data: &Vec<T>) -> usize {
+        self.write_from(data)
+    }
+    /// same as `n_write` but takes any slice, e.g. an array or part of another buffer
+    pub fn write_from(&mut self, data: &[T]) -> usize {
+        if N - self.used_count == 0 {
+            #[cfg(feature = "log")]
+            log::trace!("buffer full");
+            self.dropped += data.len();
             return 0;
         }
-        let write_count = min(data.len(),N - self.used_count.load(Ordering::Relaxed));
+        let write_count = min(data.len(),N - self.used_count);
+        self.dropped += data.len() - write_count;
 
-        let tail = self.tail.load(Ordering::Relaxed);
-        let new_tail;
-        if write_count <= (N - tail) {
-            self.buffer[tail..tail + write_count].copy_from_slice(&data[..write_count]);
+        let tail = self.tail;
+        self.copy_in(tail, &data[..write_count]);
 
-            new_tail = tail.wrapping_add(write_count);
-        } else {
-            new_tail = write_count - (N - tail);
-            self.buffer[tail..].copy_from_slice(&data[..(N - tail)]);
-            self.buffer[..new_tail].copy_from_slice(&data[(N - tail)..]);
+        self.used_count += write_count;
+        self.tail = (tail + write_count) % N;
+        write_count
+    }
+    /// write the fragments in `bufs` one after the other as if they were one slice, returns
+    /// the total number of elements written, whatever didn't fit counts as dropped
+    pub fn write_vectored(&mut self, bufs: &[&[T]]) -> usize {
+        bufs.iter().map(|buf| self.write_from(buf)).sum()
+    }
+    /// like `write_from` but also returns how many elements of `data` were dropped for lack
+    /// of room, as `(written, dropped)`, the two always add up to `data.len()`
+    pub fn saturating_write(&mut self, data: &[T]) -> (usize, usize) {
+        let written = self.write_from(data);
+        (written, data.len() - written)
+    }
+    /// like `write_from` but `data` longer than the capacity is an error instead of being
+    /// truncated, a short write because of unread elements is still `Ok`
+    pub fn write_checked(&mut self, data: &[T]) -> Result<usize, CapacityError> {
+        if data.len() > N {
+            return Err(CapacityError { requested: data.len(), capacity: N });
+        }
+        Ok(self.write_from(data))
+    }
+    /// write the front of `data` in contiguous chunks, calling `on_progress` with the
+    /// number of elements written so far after each one (at most two, when the free space
+    /// wraps around). Writes at most the current free space and returns how much of `data`
+    /// was consumed, call it again with the rest once the consumer has made room. Unlike
+    /// `write_from` the rest doesn't count as dropped.
+    pub fn write_chunked<F: FnMut(usize)>(&mut self, data: &[T], mut on_progress: F) -> usize {
+        let limit = min(data.len(), self.free());
+        let mut written = 0;
+        while written < limit {
+            let tail = self.tail;
+            let chunk = min(limit - written, N - tail);
+            written += self.write_from(&data[written..written + chunk]);
+            on_progress(written);
+        }
+        written
+    }
+    /// transactional write: either all of `data` is written, or nothing is and the
+    /// remaining free capacity is returned as the error
+    pub fn try_write_all(&mut self, data: &[T]) -> Result<(), usize> {
+        let free = self.free();
+        if data.len() > free {
+            return Err(free);
+        }
+        self.write_from(data);
+        Ok(())
+    }
+    /// write all of `data` or nothing, like `try_write_all` but with a `RingBufferError`:
+    /// `CapacityExceeded` if it could never fit, `Full` if it doesn't fit right now
+    pub fn write_exact(&mut self, data: &[T]) -> Result<(), RingBufferError> {
+        if data.len() > N {
+            return Err(RingBufferError::CapacityExceeded { requested: data.len(), capacity: N });
+        }
+        self.try_write_all(data).map_err(|_| RingBufferError::Full)
+    }
+    /// write every element of `data`, evicting the oldest unread elements when full,
+    /// returns the number of elements dropped (evicted ones plus any of `data` that
+    /// was itself overwritten because `data` is longer than `N`)
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::ptr_arg)]
+    pub fn n_write_overwrite(&mut self, data: &Vec<T>) -> usize {
+        let used_count = self.used_count;
+        let dropped = (used_count + data.len()).saturating_sub(N);
+        // only the newest N elements of data can survive
+        let data = &data[data.len().saturating_sub(N)..];
+        let write_count = data.len();
+
+        let tail = self.tail;
+        self.copy_in(tail, data);
+        let new_tail = (tail + write_count) % N;
+        let new_used_count = min(used_count + write_count, N);
+
+        self.used_count = new_used_count;
+        self.head = (new_tail + N - new_used_count) % N;
+        self.tail = new_tail;
+        self.dropped += dropped;
+        dropped
+    }
+    /// write copies of `value` into every free slot, returns the number of elements written
+    pub fn fill(&mut self, value: T) -> usize {
+        let fill_count = N - self.used_count;
+        let tail = self.tail;
+        let (wrapped, rest) = self.buffer.split_at_mut(tail);
+        for slot in rest.iter_mut().chain(wrapped).take(fill_count) {
+            slot.write(value);
+        }
+
+        self.used_count = N;
+        self.tail = (tail + fill_count) % N;
+        fill_count
+    }
+    /// write elements pulled from `iter` until the buffer is full or `iter` runs out, an
+    /// element is only pulled once there is a slot for it so pass `iter.by_ref()` to keep
+    /// the rest, returns the number of elements written
+    pub fn write_from_iter<I: Iterator<Item = T>>(&mut self, iter: I) -> usize {
+        let free = N - self.used_count;
+        let tail = self.tail;
+        let (wrapped, rest) = self.buffer.split_at_mut(tail);
+        let mut write_count = 0;
+        for (slot, elem) in rest.iter_mut().chain(wrapped).take(free).zip(iter) {
+            slot.write(elem);
+            write_count += 1;
         }
 
-        self.used_count.fetch_add(write_count, Ordering::Release);
-        self.tail.store(new_tail, Ordering::Release);
+        self.used_count += write_count;
+        self.tail = (tail + write_count) % N;
         write_count
     }
-    fn n_read(&mut self,data: &mut Vec<T>) -> usize {
+    /// clear `data` and move every buffered element into it,
+    /// returns the number of elements actually read
+    #[cfg(feature = "alloc")]
+    pub fn n_read(&mut self,data: &mut Vec<T>) -> usize {
         data.clear();
-        let read_count = self.used_count.load(Ordering::Relaxed);
+        self.read_append(data)
+    }
+    /// like `n_read` but keeps what is already in `out` and appends after it,
+    /// returns the number of elements appended
+    #[cfg(feature = "alloc")]
+    pub fn read_append(&mut self, out: &mut Vec<T>) -> usize {
+        let read_count = self.used_count;
+        if read_count == 0 {
+            #[cfg(feature = "log")]
+            log::trace!("buffer empty");
+            return 0;
+        }
+
+        let (front, back) = self.as_slices();
+        out.extend_from_slice(front);
+        out.extend_from_slice(back);
+        self.drain(read_count)
+    }
+    /// like `n_read` but only for the newest `max` elements, anything older is discarded,
+    /// returns the number of elements read
+    #[cfg(feature = "alloc")]
+    pub fn read_latest(&mut self, max: usize, out: &mut Vec<T>) -> usize {
+        let used_count = self.used_count;
+        self.drain(used_count.saturating_sub(max));
+        self.n_read(out)
+    }
+    /// like `n_read`, and then also rewind `head` and `tail` to index 0, so the next write
+    /// starts at the front of the backing array again
+    #[cfg(feature = "alloc")]
+    pub fn drain_all(&mut self, out: &mut Vec<T>) -> usize {
+        let read_count = self.n_read(out);
+        self.clear();
+        read_count
+    }
+    /// copy at most `out.len()` buffered elements into `out` without allocating,
+    /// returns the number of elements actually read
+    pub fn read_into(&mut self, out: &mut [T]) -> usize {
+        let read_count = min(out.len(), self.used_count);
         if read_count == 0 {
-            println!("buffer empty");
             return 0;
         }
 
-        let head = self.head.load(Ordering::Relaxed);
-        let new_head;
-        if read_count <= (N - head) {
-            data.extend_from_slice(&self.buffer[head..head + read_count]);
-            new_head = head.wrapping_add(read_count);
+        let (front, back) = self.as_slices();
+        let front_count = min(read_count, front.len());
+        out[..front_count].copy_from_slice(&front[..front_count]);
+        out[front_count..read_count].copy_from_slice(&back[..read_count - front_count]);
+        self.drain(read_count)
+    }
+    /// discard at most `n` of the oldest buffered elements without copying them,
+    /// returns the number of elements actually skipped
+    pub fn drain(&mut self, n: usize) -> usize {
+        let drain_count = min(n, self.used_count);
+        let head = self.head;
+
+        self.used_count -= drain_count;
+        self.head = (head + drain_count) % N;
+        drain_count
+    }
+    /// move as many of the oldest buffered elements into `dst` as it has free space for,
+    /// copying straight between the backing arrays, returns the number of elements moved
+    pub fn transfer_to<const N2: usize>(&mut self, dst: &mut RingBuffer<T, N2>) -> usize {
+        let transfer_count = min(self.len(), dst.free());
+        let (front, back) = self.as_slices();
+        let front_count = min(transfer_count, front.len());
+        dst.write_from(&front[..front_count]);
+        dst.write_from(&back[..transfer_count - front_count]);
+        self.drain(transfer_count)
+    }
+    /// read the oldest element, `Empty` if there is none
+    pub fn read_one(&mut self) -> Result<T, RingBufferError> {
+        let elem = *self.peek().ok_or(RingBufferError::Empty)?;
+        self.drain(1);
+        Ok(elem)
+    }
+    /// all-or-nothing read of exactly `M` elements into an array, `None` and nothing consumed
+    /// if fewer are buffered
+    pub fn read_array<const M: usize>(&mut self) -> Option<[T; M]> {
+        if self.used_count < M {
+            return None;
+        }
+        let array = core::array::from_fn(|i| self[i]);
+        self.drain(M);
+        Some(array)
+    }
+    /// all-or-nothing read: clear `out` and move exactly `n` elements into it if that many
+    /// are buffered, otherwise leave both `out` and the buffer untouched and return false
+    #[cfg(feature = "alloc")]
+    pub fn read_exact(&mut self, n: usize, out: &mut Vec<T>) -> bool {
+        if self.used_count < n {
+            return false;
+        }
+        out.clear();
+        out.extend(self.iter().take(n));
+        self.drain(n);
+        true
+    }
+    /// the oldest buffered element, without consuming it
+    pub fn peek(&self) -> Option<&T> {
+        self.iter().next()
+    }
+    /// the newest buffered element, the one in the slot just before `tail`,
+    /// without consuming it
+    pub fn peek_back(&self) -> Option<&T> {
+        let (front, back) = self.as_slices();
+        back.last().or(front.last())
+    }
+    /// copy of the oldest buffered element, see `peek`
+    pub fn first(&self) -> Option<T> {
+        self.peek().copied()
+    }
+    /// copy of the newest buffered element, see `peek_back`
+    pub fn last(&self) -> Option<T> {
+        self.peek_back().copied()
+    }
+    /// like `n_read` but for at most `n` elements and without consuming them,
+    /// returns the number of elements copied into `out`
+    #[cfg(feature = "alloc")]
+    pub fn peek_n(&self, n: usize, out: &mut Vec<T>) -> usize {
+        out.clear();
+        out.extend(self.iter().take(n));
+        out.len()
+    }
+    /// copy of the buffered elements in FIFO order, unlike `n_read` nothing is consumed
+    #[cfg(feature = "alloc")]
+    pub fn to_vec(&self) -> Vec<T> {
+        let (front, back) = self.as_slices();
+        [front, back].concat()
+    }
+    /// number of buffered elements for which `f` returns true, nothing is consumed
+    pub fn count<F: FnMut(&T) -> bool>(&self, mut f: F) -> usize {
+        self.iter().filter(|elem| f(elem)).count()
+    }
+
+    /// number of elements currently buffered
+    pub fn len(&self) -> usize {
+        self.used_count
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+    /// number of elements that can be written before the buffer is full
+    pub fn free(&self) -> usize {
+        N - self.len()
+    }
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    /// total number of elements lost: evicted by `n_write_overwrite`, or not written
+    /// because `n_write`/`write_from` found too little room, a rejected `try_write_all`
+    /// leaves `data` with the caller and doesn't count
+    pub fn dropped_count(&self) -> usize {
+        self.dropped
+    }
+
+    /// check the bookkeeping, for tests and debugging: `head` and `tail` are slot indices and
+    /// `tail` sits `used_count` slots past `head`, returns which rule is broken if any
+    pub fn check_invariants(&self) -> Result<(), &'static str> {
+        let head = self.head;
+        let tail = self.tail;
+        let used_count = self.used_count;
+        if used_count > N {
+            return Err("used_count is larger than the capacity");
+        }
+        if head >= N {
+            return Err("head is not a slot index");
+        }
+        if tail >= N {
+            return Err("tail is not a slot index");
+        }
+        if tail != (head + used_count) % N {
+            return Err("tail is not used_count slots past head");
+        }
+        Ok(())
+    }
+
+    /// drop every buffered element and rewind to index 0,
+    /// the backing array is left as-is, stale slots are simply overwritten by later writes
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.tail = 0;
+        self.used_count = 0;
+    }
+    /// keep only the buffered elements for which `f` returns true, in their FIFO order,
+    /// the survivors are moved up towards `head`. Removed elements don't count as dropped.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let head = self.head;
+        let mut kept = 0;
+        for read in 0..self.len() {
+            let elem = self[read];
+            if f(&elem) {
+                // kept <= read, so this slot has already been looked at
+                self.buffer[(head + kept) % N].write(elem);
+                kept += 1;
+            }
+        }
+
+        self.used_count = kept;
+        self.tail = (head + kept) % N;
+    }
+    /// reverse the order of the buffered elements in place, the next read returns the
+    /// newest one first
+    pub fn reverse(&mut self) {
+        let head = self.head;
+        let len = self.len();
+        for i in 0..len / 2 {
+            self.buffer.swap((head + i) % N, (head + len - 1 - i) % N);
+        }
+    }
+    /// overwrite the element at `logical_index`, counted from the oldest element as in
+    /// `ringbuffer[i]`, returns the previous value or `None` if the index isn't buffered
+    pub fn replace(&mut self, logical_index: usize, value: T) -> Option<T> {
+        if logical_index >= self.len() {
+            return None;
+        }
+        let previous = self[logical_index];
+        let head = self.head;
+        self.buffer[(head + logical_index) % N].write(value);
+        Some(previous)
+    }
+    /// call `f` on each buffered element in FIFO order, changing them in place without
+    /// consuming anything, free slots are never visited
+    pub fn for_each_mut<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        let head = self.head;
+        for i in 0..self.len() {
+            let mut elem = self[i];
+            f(&mut elem);
+            self.buffer[(head + i) % N].write(elem);
+        }
+    }
+
+    /// iterate over the buffered elements in FIFO order without consuming them
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        let (front, back) = self.as_slices();
+        front.iter().chain(back.iter())
+    }
+
+    /// the buffered elements as two contiguous slices in FIFO order: from `head` towards the
+    /// end of the backing array, then the wrapped part from index 0, which is empty when the
+    /// data doesn't wrap
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let head = self.head;
+        let len = self.len();
+        let (front, back) = if len <= (N - head) {
+            (&self.buffer[head..head + len], &self.buffer[..0])
         } else {
-            new_head = read_count - (N - head);
-            data.extend_from_slice(&self.buffer[head..]);
-            data.extend_from_slice(&self.buffer[..new_head]);
+            (&self.buffer[head..], &self.buffer[..len - (N - head)])
+        };
+        // SAFETY: both slices lie within the live region, whose slots are all initialized,
+        // and MaybeUninit<T> has the same layout as T
+        unsafe {
+            (
+                &*(front as *const [MaybeUninit<T>] as *const [T]),
+                &*(back as *const [MaybeUninit<T>] as *const [T]),
+            )
         }
+    }
 
-        self.used_count.fetch_sub(read_count, Ordering::Release);
-        self.head.store(new_head, Ordering::Release);
-        read_count
+    /// rotate the backing array so the buffered elements start at index 0, like
+    /// `VecDeque::make_contiguous`, and return them as a single slice in FIFO order
+    pub fn make_contiguous(&mut self) -> &[T] {
+        let len = self.len();
+        self.buffer.rotate_left(self.head);
+        // the rotation keeps the reserved slots right behind `tail`
+        let (reserved_at, reserved) = self.reservation;
+        if reserved_at == self.tail {
+            self.reservation = (len % N, reserved);
+        }
+        self.head = 0;
+        self.tail = len % N;
+        self.as_slices().0
+    }
+
+    /// copy `data` into the slots starting at `tail`, wrapping around to index 0,
+    /// `data` must not be longer than `N`
+    fn copy_in(&mut self, tail: usize, data: &[T]) {
+        let tail = tail % N;
+        let (front, back) = data.split_at(min(data.len(), N - tail));
+        for (slot, elem) in self.buffer[tail..].iter_mut().zip(front) {
+            slot.write(*elem);
+        }
+        for (slot, elem) in self.buffer.iter_mut().zip(back) {
+            slot.write(*elem);
+        }
+    }
+}
+
+impl<T, const N: usize> RingBuffer<T, N>
+where
+    T: Copy + Default
+{
+    /// up to `n` free slots to write into in place, only the first contiguous run of them
+    /// when the free space wraps around, so the slice may be shorter than `n` even though
+    /// there is more room. The slots start out as `T::default()`, nothing is buffered until
+    /// `commit`.
+    pub fn reserve(&mut self, n: usize) -> &mut [T] {
+        let tail = self.tail;
+        let free = N - self.used_count;
+        let len = min(n, min(free, N - tail));
+        let slots = &mut self.buffer[tail..tail + len];
+        for slot in slots.iter_mut() {
+            slot.write(T::default());
+        }
+        self.reservation = (tail, len);
+        // SAFETY: every slot was just initialized, and MaybeUninit<T> has the same layout as T
+        unsafe { &mut *(slots as *mut [MaybeUninit<T>] as *mut [T]) }
+    }
+    /// publish the first `n` slots of the last `reserve` as buffered elements, panics if
+    /// that reserved fewer slots or another write happened in between
+    pub fn commit(&mut self, n: usize) {
+        let tail = self.tail;
+        let free = N - self.used_count;
+        // an overwriting write may have gone all the way round and filled the reserved slots
+        let (reserved_at, reserved) = self.reservation;
+        let reserved = if reserved_at == tail { min(reserved, free) } else { 0 };
+        assert!(n <= reserved, "cannot commit {} slots, only {} are reserved", n, reserved);
+        self.reservation = (tail, 0);
+
+        self.used_count += n;
+        self.tail = (tail + n) % N;
+    }
+}
+
+impl<T, const N: usize> RingBuffer<T, N>
+where
+    T: Copy + PartialEq
+{
+    /// whether `needle` is buffered
+    pub fn contains(&self, needle: &T) -> bool {
+        self.position(needle).is_some()
+    }
+    /// index of the oldest buffered element equal to `needle`, counted from the oldest
+    /// element as in `ringbuffer[i]`
+    pub fn position(&self, needle: &T) -> Option<usize> {
+        self.iter().position(|elem| elem == needle)
     }
 }
 
+impl<T, const N: usize> RingBuffer<T, N>
+where
+    T: Copy + PartialOrd
+{
+    /// smallest buffered element, the oldest one of equal minima, `None` when the buffer is
+    /// empty. Elements that don't compare, like NaN, aren't treated specially.
+    pub fn min(&self) -> Option<T> {
+        self.iter().copied().reduce(|min, elem| if elem < min { elem } else { min })
+    }
+    /// largest buffered element, the oldest one of equal maxima, `None` when the buffer is
+    /// empty. Elements that don't compare, like NaN, aren't treated specially.
+    pub fn max(&self) -> Option<T> {
+        self.iter().copied().reduce(|max, elem| if elem > max { elem } else { max })
+    }
+}
 
-#[cfg(test)]
+impl<T, const N: usize> RingBuffer<T, N>
+where
+    T: Copy + Sum
+{
+    /// sum of the buffered elements, e.g. for a moving sum over the last `N` samples
+    pub fn sum(&self) -> T {
+        self.iter().copied().sum()
+    }
+}
+
+impl<T, const N: usize> RingBuffer<T, N>
+where
+    T: Copy + Into<f64>
+{
+    /// mean of the buffered elements, `None` when the buffer is empty
+    pub fn mean(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        let sum: f64 = self.iter().map(|&elem| elem.into()).sum();
+        Some(sum / self.len() as f64)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Copy, const N: usize> crate::RingBuffer<T> for RingBuffer<T, N> {
+    fn write(&mut self, data: &[T]) -> usize {
+        self.write_from(data)
+    }
+    fn read(&mut self, data: &mut Vec<T>) -> usize {
+        self.n_read(data)
+    }
+    fn len(&self) -> usize {
+        RingBuffer::len(self)
+    }
+    fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T, const N: usize> Clone for RingBuffer<T, N>
+where
+    T: Copy
+{
+    fn clone(&self) -> Self {
+        Self {
+            buffer: self.buffer,
+            head: self.head,
+            tail: self.tail,
+            used_count: self.used_count,
+            dropped: self.dropped,
+            reservation: self.reservation,
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> PushSink<T> for RingBuffer<T, N> {
+    fn try_push(&mut self, value: T) -> Result<(), T> {
+        self.try_write_all(&[value]).map_err(|_| value)
+    }
+    fn free(&self) -> usize {
+        RingBuffer::free(self)
+    }
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N>
+where
+    T: Copy
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// buffers are equal when they hold the same elements in the same FIFO order, where the
+/// data physically sits in the backing array and the dropped count don't matter
+impl<T, const N: usize> PartialEq for RingBuffer<T, N>
+where
+    T: Copy + PartialEq
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T, const N: usize> Eq for RingBuffer<T, N>
+where
+    T: Copy + Eq
+{
+}
+
+/// hashes the buffered elements in FIFO order like a slice of them would be, consistent
+/// with `PartialEq`
+impl<T, const N: usize> Hash for RingBuffer<T, N>
+where
+    T: Copy + Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for elem in self.iter() {
+            elem.hash(state);
+        }
+    }
+}
+
+/// `ringbuffer[0]` is the oldest buffered element, `ringbuffer[len - 1]` the newest
+impl<T, const N: usize> Index<usize> for RingBuffer<T, N>
+where
+    T: Copy
+{
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        let len = self.len();
+        assert!(index < len, "index out of bounds: the len is {} but the index is {}", len, index);
+        let (front, back) = self.as_slices();
+        if index < front.len() {
+            &front[index]
+        } else {
+            &back[index - front.len()]
+        }
+    }
+}
+
+/// ring buffer of bytes, with `std` usable anywhere a `Read` or `Write` is expected
+pub type ByteRingBuffer<const N: usize> = RingBuffer<u8, N>;
+
+impl<const N: usize> RingBuffer<u8, N> {
+    /// the buffered bytes in FIFO order, without consuming them
+    #[cfg(feature = "alloc")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+    /// a buffer holding the first `N` of `bytes`, the inverse of `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self::from_slice(bytes)
+    }
+}
+
+/// a byte ring buffer works as an in-memory pipe, a write stores as many bytes as fit
+/// (`Ok(0)` when full) and a read returns `Ok(0)` once it is empty
+#[cfg(feature = "std")]
+impl<const N: usize> io::Write for RingBuffer<u8, N> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.write_from(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize> io::Read for RingBuffer<u8, N> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.read_into(buf))
+    }
+}
+
+/// only the buffered elements are printed, stale slots of the backing array are skipped
+impl<T, const N: usize> fmt::Debug for RingBuffer<T, N>
+where
+    T: Copy + fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// lists both halves as one, without collecting them first
+        struct Data<'a, T>(&'a [T], &'a [T]);
+
+        impl<T: fmt::Debug> fmt::Debug for Data<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_list().entries(self.0.iter().chain(self.1)).finish()
+            }
+        }
+
+        let (front, back) = self.as_slices();
+        f.debug_struct("RingBuffer")
+            .field("len", &self.len())
+            .field("cap", &N)
+            .field("data", &Data(front, back))
+            .finish()
+    }
+}
+
+/// serialized as the capacity plus the buffered elements in FIFO order
+#[cfg(feature = "serde")]
+impl<T, const N: usize> serde::Serialize for RingBuffer<T, N>
+where
+    T: Copy + serde::Serialize
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("RingBuffer", 2)?;
+        state.serialize_field("capacity", &N)?;
+        state.serialize_field("data", &self.to_vec())?;
+        state.end()
+    }
+}
+
+/// the buffer is rebuilt starting at index 0, a capacity other than `N` is rejected
+#[cfg(feature = "serde")]
+impl<'de, T, const N: usize> serde::Deserialize<'de> for RingBuffer<T, N>
+where
+    T: Copy + serde::Deserialize<'de>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "RingBuffer")]
+        struct Repr<T> {
+            capacity: usize,
+            data: Vec<T>,
+        }
+
+        let repr = Repr::<T>::deserialize(deserializer)?;
+        if repr.capacity != N {
+            return Err(D::Error::custom(alloc::format!(
+                "capacity mismatch: expected {}, found {}", N, repr.capacity
+            )));
+        }
+        if repr.data.len() > N {
+            return Err(D::Error::invalid_length(repr.data.len(), &"at most capacity elements"));
+        }
+        let mut ringbuffer = Self::new();
+        ringbuffer.write_from(&repr.data);
+        Ok(ringbuffer)
+    }
+}
+
+/// draining iterator returned by `RingBuffer::into_iter`, yields elements in FIFO order
+pub struct IntoIter<T, const N: usize> {
+    ringbuffer: RingBuffer<T, N>,
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N>
+where
+    T: Copy
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let elem = *self.ringbuffer.iter().next()?;
+        self.ringbuffer.drain(1);
+        Some(elem)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.ringbuffer.len();
+        (len, Some(len))
+    }
+}
+
+impl<T, const N: usize> IntoIterator for RingBuffer<T, N>
+where
+    T: Copy
+{
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> IntoIter<T, N> {
+        IntoIter { ringbuffer: self }
+    }
+}
+
+/// keeps the first `N` items like `from_slice` does, the iterator is not advanced past them
+/// and the excess is neither buffered nor counted as dropped
+impl<T, const N: usize> FromIterator<T> for RingBuffer<T, N>
+where
+    T: Copy
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut ringbuffer = Self::new();
+        let mut count = 0;
+        for (slot, elem) in ringbuffer.buffer.iter_mut().zip(iter) {
+            slot.write(elem);
+            count += 1;
+        }
+        ringbuffer.used_count = count;
+        ringbuffer.tail = count % N;
+        ringbuffer
+    }
+}
+
+/// like `from_slice`, but a `vec` longer than the capacity is handed back unchanged
+/// instead of being truncated
+#[cfg(feature = "alloc")]
+impl<T, const N: usize> TryFrom<Vec<T>> for RingBuffer<T, N>
+where
+    T: Copy
+{
+    type Error = Vec<T>;
+
+    fn try_from(vec: Vec<T>) -> Result<Self, Vec<T>> {
+        if vec.len() > N {
+            return Err(vec);
+        }
+        Ok(Self::from_slice(&vec))
+    }
+}
+
+/// writes every item like `n_write_overwrite`, evicting the oldest unread elements when full,
+/// so afterwards the newest `N` elements are buffered. Evictions count as dropped.
+impl<T, const N: usize> Extend<T> for RingBuffer<T, N>
+where
+    T: Copy
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut used_count = self.used_count;
+        let mut tail = self.tail;
+        let mut dropped = 0;
+        for elem in iter {
+            self.buffer[tail].write(elem);
+            tail = (tail + 1) % N;
+            if used_count == N {
+                dropped += 1;
+            } else {
+                used_count += 1;
+            }
+        }
+
+        self.used_count = used_count;
+        self.head = (tail + N - used_count) % N;
+        self.tail = tail;
+        self.dropped += dropped;
+    }
+}
+
+
+#[cfg(all(test, feature = "alloc"))]
 mod test {
     use super::RingBuffer;
 
@@ -94,6 +924,7 @@ mod test {
         assert_eq!(ringbuffer.n_write(&data), 2);
         assert_eq!(ringbuffer.n_read(&mut result), 10);
         assert_eq!(ringbuffer.n_read(&mut result), 0);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
     }
 
     #[derive(Clone,Copy,PartialEq,Debug)]
@@ -131,4 +962,1281 @@ mod test {
         assert_eq!(ringbuffer.n_read(&mut result), 0);
     }   
 
+    #[test]
+    fn len_and_capacity() {
+        let mut ringbuffer = RingBuffer::<i32, 10>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.capacity(), 10);
+        assert_eq!(ringbuffer.len(), 0);
+        assert!(ringbuffer.is_empty());
+        assert!(!ringbuffer.is_full());
+
+        ringbuffer.n_write(&vec![1; 6]);
+        assert_eq!(ringbuffer.len(), 6);
+        ringbuffer.n_read(&mut result);
+        assert!(ringbuffer.is_empty());
+
+        // tail wraps past the end of the backing array
+        ringbuffer.n_write(&vec![2; 7]);
+        assert_eq!(ringbuffer.len(), 7);
+        assert!(!ringbuffer.is_empty());
+        ringbuffer.n_write(&vec![3; 5]);
+        assert_eq!(ringbuffer.len(), 10);
+        assert!(ringbuffer.is_full());
+
+        assert_eq!(ringbuffer.n_read(&mut result), 10);
+        assert_eq!(ringbuffer.len(), 0);
+        assert!(ringbuffer.is_empty());
+        assert_eq!(ringbuffer.capacity(), 10);
+    }
+
+    #[test]
+    fn clear() {
+        let mut ringbuffer = RingBuffer::<i32, 10>::new();
+        let mut result = Vec::new();
+        ringbuffer.n_write(&vec![1; 7]);
+        ringbuffer.n_read(&mut result);
+        ringbuffer.n_write(&vec![2; 5]);
+
+        ringbuffer.clear();
+        assert!(ringbuffer.is_empty());
+        assert_eq!(ringbuffer.n_read(&mut result), 0);
+
+        // a fresh write starts again at index 0
+        assert_eq!(ringbuffer.n_write(&vec![3, 4]), 2);
+        assert_eq!(ringbuffer.head, 0);
+        assert_eq!(ringbuffer.as_slices().0, &[3, 4]);
+        assert_eq!(ringbuffer.tail, 2);
+        assert_eq!(ringbuffer.n_read(&mut result), 2);
+        assert_eq!(result, vec![3, 4]);
+    }
+
+    #[test]
+    fn overwrite() {
+        let mut ringbuffer = RingBuffer::<i32, 4>::new();
+        let mut result = Vec::new();
+
+        // more data than capacity, only the newest N survive
+        assert_eq!(ringbuffer.n_write_overwrite(&vec![1, 2, 3, 4, 5, 6]), 2);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![3, 4, 5, 6]);
+
+        // partially overlaps unread data
+        assert_eq!(ringbuffer.n_write(&vec![1, 2, 3]), 3);
+        assert_eq!(ringbuffer.n_write_overwrite(&vec![4, 5]), 1);
+        assert_eq!(ringbuffer.len(), 4);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![2, 3, 4, 5]);
+
+        // fits without evicting anything
+        assert_eq!(ringbuffer.n_write_overwrite(&vec![6]), 0);
+        assert_eq!(ringbuffer.n_write_overwrite(&vec![7, 8, 9, 10, 11]), 2);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn read_into_slice() {
+        let mut ringbuffer = RingBuffer::<i32, 10>::new();
+        let mut result = Vec::new();
+        ringbuffer.n_write(&vec![0; 7]);
+        ringbuffer.n_read(&mut result);
+
+        // live data wraps: 3 elements at the end, 3 at the front
+        ringbuffer.n_write(&vec![1, 2, 3, 4, 5, 6]);
+
+        // out smaller than available data
+        let mut out = [0; 4];
+        assert_eq!(ringbuffer.read_into(&mut out), 4);
+        assert_eq!(out, [1, 2, 3, 4]);
+        assert_eq!(ringbuffer.len(), 2);
+
+        // out larger than available data
+        let mut out = [0; 8];
+        assert_eq!(ringbuffer.read_into(&mut out), 2);
+        assert_eq!(out, [5, 6, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(ringbuffer.read_into(&mut out), 0);
+
+        ringbuffer.n_write(&vec![7, 8]);
+        assert_eq!(ringbuffer.n_read(&mut result), 2);
+        assert_eq!(result, vec![7, 8]);
+    }
+
+    #[test]
+    fn write_from_slice() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+
+        assert_eq!(ringbuffer.write_from(&[1, 2, 3]), 3);
+        assert_eq!(ringbuffer.n_read(&mut result), 3);
+
+        // wraps around and is capped by the remaining capacity
+        assert_eq!(ringbuffer.write_from(&[4, 5, 6, 7]), 4);
+        assert_eq!(ringbuffer.write_from(&[8, 9][..]), 1);
+        assert_eq!(ringbuffer.write_from(&[10]), 0);
+        assert_eq!(ringbuffer.n_read(&mut result), 5);
+        assert_eq!(result, vec![4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn iter() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.iter().count(), 0);
+
+        ringbuffer.write_from(&[0, 0, 0]);
+        ringbuffer.n_read(&mut result);
+        // wraps past index N-1, stale slots must not show up
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+        assert_eq!(ringbuffer.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+
+        // iterating doesn't consume anything
+        assert_eq!(ringbuffer.len(), 4);
+        assert_eq!(ringbuffer.head, 3);
+        assert_eq!(ringbuffer.tail, 2);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        ringbuffer.write_from(&[1, 2, 3]);
+        assert_eq!(ringbuffer.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // crossing the wrap boundary
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[0, 0, 0, 0]);
+        ringbuffer.n_read(&mut result);
+        ringbuffer.write_from(&[1, 2, 3, 4, 5]);
+        let mut iter = ringbuffer.into_iter();
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn debug() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        assert_eq!(format!("{:?}", ringbuffer), "RingBuffer { len: 0, cap: 5, data: [] }");
+
+        ringbuffer.write_from(&[9, 9, 9, 9]);
+        ringbuffer.n_read(&mut result);
+        ringbuffer.write_from(&[1, 2, 3]);
+        assert_eq!(format!("{:?}", ringbuffer), "RingBuffer { len: 3, cap: 5, data: [1, 2, 3] }");
+    }
+
+    #[test]
+    fn clone() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+        ringbuffer.read_into(&mut [0; 2]);
+        ringbuffer.write_from(&[5, 6]);
+
+        let mut snapshot = ringbuffer.clone();
+        let mut snapshot_result = Vec::new();
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(snapshot.n_read(&mut snapshot_result), 4);
+        assert_eq!(result, vec![3, 4, 5, 6]);
+        assert_eq!(snapshot_result, result);
+
+        // both keep going independently from the same position
+        ringbuffer.write_from(&[7]);
+        snapshot.write_from(&[8]);
+        ringbuffer.n_read(&mut result);
+        snapshot.n_read(&mut snapshot_result);
+        assert_eq!(result, vec![7]);
+        assert_eq!(snapshot_result, vec![8]);
+    }
+
+    #[test]
+    fn from_slice() {
+        let mut ringbuffer = RingBuffer::<i32, 10>::from_slice(&[1, 2, 3, 4, 5]);
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.len(), 5);
+        assert_eq!(ringbuffer.head, 0);
+        assert_eq!(ringbuffer.n_read(&mut result), 5);
+        assert_eq!(result, vec![1, 2, 3, 4, 5]);
+
+        // extra elements are ignored
+        let mut ringbuffer = RingBuffer::<i32, 3>::from_slice(&[1, 2, 3, 4, 5]);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.n_read(&mut result), 3);
+        assert_eq!(result, vec![1, 2, 3]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[0, 0, 0]);
+        ringbuffer.n_read(&mut result);
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+
+        let json = serde_json::to_string(&ringbuffer).unwrap();
+        assert_eq!(json, r#"{"capacity":5,"data":[1,2,3,4]}"#);
+
+        let mut restored: RingBuffer<i32, 5> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.head, 0);
+        assert_eq!(restored.tail, 4);
+        let mut restored_result = Vec::new();
+        assert_eq!(restored.n_read(&mut restored_result), 4);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(restored_result, result);
+
+        assert!(serde_json::from_str::<RingBuffer<i32, 4>>(&json).is_err());
+    }
+
+    #[test]
+    fn drain() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[0, 0, 0]);
+        ringbuffer.n_read(&mut result);
+        ringbuffer.write_from(&[1, 2, 3, 4, 5]);
+
+        // skips across the wrap boundary
+        assert_eq!(ringbuffer.drain(3), 3);
+        assert_eq!(ringbuffer.len(), 2);
+        assert_eq!(ringbuffer.n_read(&mut result), 2);
+        assert_eq!(result, vec![4, 5]);
+
+        ringbuffer.write_from(&[6, 7]);
+        assert_eq!(ringbuffer.drain(10), 2);
+        assert!(ringbuffer.is_empty());
+        assert_eq!(ringbuffer.drain(1), 0);
+    }
+
+    #[test]
+    fn read_exact() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = vec![9];
+        ringbuffer.write_from(&[0, 0, 0]);
+        ringbuffer.drain(3);
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+
+        // n > used_count
+        assert!(!ringbuffer.read_exact(5, &mut result));
+        assert_eq!(result, vec![9]);
+        assert_eq!(ringbuffer.len(), 4);
+
+        // n < used_count, across the wrap boundary
+        assert!(ringbuffer.read_exact(3, &mut result));
+        assert_eq!(result, vec![1, 2, 3]);
+
+        // n == used_count
+        assert!(ringbuffer.read_exact(1, &mut result));
+        assert_eq!(result, vec![4]);
+        assert!(ringbuffer.is_empty());
+    }
+
+    #[test]
+    fn as_slices() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        assert_eq!(ringbuffer.as_slices(), (&[][..], &[][..]));
+
+        // contiguous
+        ringbuffer.write_from(&[1, 2, 3]);
+        let (front, back) = ringbuffer.as_slices();
+        assert_eq!(front, &[1, 2, 3]);
+        assert!(back.is_empty());
+        assert_eq!(front.len() + back.len(), ringbuffer.len());
+
+        // wrapped
+        ringbuffer.drain(2);
+        ringbuffer.write_from(&[4, 5, 6, 7]);
+        let (front, back) = ringbuffer.as_slices();
+        assert_eq!(front, &[3, 4, 5]);
+        assert_eq!(back, &[6, 7]);
+        assert_eq!(front.len() + back.len(), ringbuffer.len());
+    }
+
+    #[test]
+    fn make_contiguous() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        assert!(ringbuffer.make_contiguous().is_empty());
+
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+        ringbuffer.drain(3);
+        ringbuffer.write_from(&[5, 6, 7]);
+        assert_eq!(ringbuffer.as_slices(), (&[4, 5][..], &[6, 7][..]));
+        assert_eq!(ringbuffer.make_contiguous(), &[4, 5, 6, 7]);
+        assert_eq!(ringbuffer.as_slices(), (&[4, 5, 6, 7][..], &[][..]));
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+
+        // full and wrapped, then carries on as usual
+        ringbuffer.write_from(&[8]);
+        ringbuffer.drain(2);
+        ringbuffer.write_from(&[9, 10]);
+        assert_eq!(ringbuffer.make_contiguous(), &[6, 7, 8, 9, 10]);
+        assert!(ringbuffer.as_slices().1.is_empty());
+        assert_eq!(ringbuffer.write_from(&[11]), 0);
+        ringbuffer.drain(1);
+        assert_eq!(ringbuffer.write_from(&[11]), 1);
+        assert_eq!(ringbuffer.n_read(&mut result), 5);
+        assert_eq!(result, vec![7, 8, 9, 10, 11]);
+    }
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct NoDefault(u8);
+
+    #[test]
+    fn without_default() {
+        let mut ringbuffer = RingBuffer::<NoDefault, 3>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.n_read(&mut result), 0);
+        assert_eq!(ringbuffer.iter().count(), 0);
+
+        ringbuffer.write_from(&[NoDefault(1), NoDefault(2)]);
+        assert_eq!(ringbuffer.n_read(&mut result), 2);
+        ringbuffer.write_from(&[NoDefault(3), NoDefault(4), NoDefault(5)]);
+        assert_eq!(ringbuffer.iter().copied().collect::<Vec<_>>(), vec![NoDefault(3), NoDefault(4), NoDefault(5)]);
+        assert_eq!(ringbuffer.n_read(&mut result), 3);
+        assert_eq!(result, vec![NoDefault(3), NoDefault(4), NoDefault(5)]);
+    }
+
+    #[test]
+    fn index() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        ringbuffer.write_from(&[0, 0, 0]);
+        ringbuffer.drain(3);
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+
+        assert_eq!(ringbuffer[0], 1);
+        assert_eq!(ringbuffer[1], 2);
+        assert_eq!(ringbuffer[2], 3);
+        assert_eq!(ringbuffer[3], 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the len is 2 but the index is 2")]
+    fn index_out_of_bounds() {
+        let ringbuffer = RingBuffer::<i32, 5>::from_slice(&[1, 2]);
+        let _ = ringbuffer[2];
+    }
+
+    #[test]
+    fn peek() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut peeked = Vec::new();
+        assert_eq!(ringbuffer.peek(), None);
+        assert_eq!(ringbuffer.peek_n(3, &mut peeked), 0);
+
+        ringbuffer.write_from(&[0, 0, 0]);
+        ringbuffer.drain(3);
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+
+        // repeated peeks see the same data
+        assert_eq!(ringbuffer.peek(), Some(&1));
+        assert_eq!(ringbuffer.peek(), Some(&1));
+        assert_eq!(ringbuffer.peek_n(3, &mut peeked), 3);
+        assert_eq!(peeked, vec![1, 2, 3]);
+        assert_eq!(ringbuffer.peek_n(10, &mut peeked), 4);
+        assert_eq!(peeked, vec![1, 2, 3, 4]);
+
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn try_write_all() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.try_write_all(&[1, 2]), Ok(()));
+
+        // overflow, nothing is written
+        assert_eq!(ringbuffer.try_write_all(&[3, 4, 5, 6]), Err(3));
+        assert_eq!(ringbuffer.len(), 2);
+
+        // exact fit
+        assert_eq!(ringbuffer.try_write_all(&[3, 4, 5]), Ok(()));
+        assert!(ringbuffer.is_full());
+
+        // empty input always fits
+        assert_eq!(ringbuffer.try_write_all(&[]), Ok(()));
+        assert_eq!(ringbuffer.n_read(&mut result), 5);
+        assert_eq!(result, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_pipe() {
+        use std::io::{Read, Write};
+        use super::ByteRingBuffer;
+
+        let message = b"the quick brown fox jumps over the lazy dog";
+        let mut ringbuffer = ByteRingBuffer::<8>::new();
+        let mut received = Vec::new();
+        let mut chunk = [0; 5];
+
+        // 8 bytes of room read back 5 at a time keeps wrapping around
+        let mut sent = 0;
+        while received.len() < message.len() {
+            sent += ringbuffer.write(&message[sent..]).unwrap();
+            let n = ringbuffer.read(&mut chunk).unwrap();
+            received.extend_from_slice(&chunk[..n]);
+        }
+        ringbuffer.flush().unwrap();
+        assert_eq!(received, message);
+
+        // hooks into std::io helpers
+        ringbuffer.write_all(b"pipe").unwrap();
+        let mut out = Vec::new();
+        assert_eq!(std::io::copy(&mut ringbuffer, &mut out).unwrap(), 4);
+        assert_eq!(out, b"pipe");
+    }
+
+
+    /// reruns itself as a child test process so the child's real stdout can be inspected,
+    /// the test harness would otherwise capture it
+    #[cfg(feature = "std")]
+    #[test]
+    fn silent_when_full_or_empty() {
+        use std::process::Command;
+
+        if std::env::var_os("RINGBUFFER_SILENT_CHILD").is_some() {
+            let mut ringbuffer = RingBuffer::<i32, 2>::new();
+            let mut result = Vec::new();
+            assert_eq!(ringbuffer.n_write(&vec![1, 2, 3]), 2);
+            assert_eq!(ringbuffer.n_write(&vec![4]), 0);
+            assert_eq!(ringbuffer.n_read(&mut result), 2);
+            assert_eq!(ringbuffer.n_read(&mut result), 0);
+            return;
+        }
+
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args(["ringbuffer::test::silent_when_full_or_empty", "--exact", "--nocapture"])
+            .env("RINGBUFFER_SILENT_CHILD", "1")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}", stdout);
+        // the child did run, and printed nothing but the harness' own lines
+        assert!(stdout.contains("1 passed"), "{}", stdout);
+        assert!(!stdout.contains("buffer full"), "{}", stdout);
+        assert!(!stdout.contains("buffer empty"), "{}", stdout);
+    }
+
+    #[test]
+    fn dropped_count() {
+        let mut ringbuffer = RingBuffer::<i32, 4>::new();
+        let mut result = Vec::new();
+
+        // bounded writes count whatever didn't fit
+        assert_eq!(ringbuffer.n_write(&vec![1, 2, 3]), 3);
+        assert_eq!(ringbuffer.dropped_count(), 0);
+        assert_eq!(ringbuffer.n_write(&vec![4, 5, 6]), 1);
+        assert_eq!(ringbuffer.dropped_count(), 2);
+        assert_eq!(ringbuffer.write_from(&[7, 8]), 0);
+        assert_eq!(ringbuffer.dropped_count(), 4);
+        assert_eq!(ringbuffer.try_write_all(&[9]), Err(0));
+        assert_eq!(ringbuffer.dropped_count(), 4);
+
+        // overwrites count the evicted elements
+        ringbuffer.n_read(&mut result);
+        ringbuffer.n_write(&vec![1, 2, 3]);
+        assert_eq!(ringbuffer.n_write_overwrite(&vec![4, 5, 6]), 2);
+        assert_eq!(ringbuffer.dropped_count(), 6);
+        assert_eq!(ringbuffer.n_write_overwrite(&vec![7; 6]), 6);
+        assert_eq!(ringbuffer.dropped_count(), 12);
+
+        // it's a running total, reading doesn't reset it
+        ringbuffer.n_read(&mut result);
+        assert_eq!(ringbuffer.clone().dropped_count(), 12);
+    }
+
+    #[test]
+    fn eq_by_contents() {
+        let mut at_start = RingBuffer::<i32, 5>::new();
+        at_start.n_write(&vec![3, 4, 5]);
+
+        // same data, but split across the end of the backing array
+        let mut wrapped = RingBuffer::<i32, 5>::new();
+        wrapped.n_write(&vec![0, 1, 2, 3]);
+        wrapped.drain(3);
+        wrapped.n_write(&vec![4, 5]);
+        assert_ne!(wrapped.as_slices().1.len(), 0);
+        assert_eq!(at_start, wrapped);
+
+        wrapped.drain(1);
+        assert_ne!(at_start, wrapped);
+        wrapped.n_write(&vec![6]);
+        assert_ne!(at_start, wrapped);
+
+        assert_eq!(RingBuffer::<i32, 5>::new(), RingBuffer::<i32, 5>::new());
+    }
+
+    #[test]
+    fn to_vec() {
+        let mut ringbuffer = RingBuffer::<i32, 4>::new();
+        let mut result = Vec::new();
+        assert!(ringbuffer.to_vec().is_empty());
+
+        ringbuffer.n_write(&vec![1, 2, 3]);
+        ringbuffer.drain(2);
+        ringbuffer.n_write(&vec![4, 5, 6]);
+        assert_eq!(ringbuffer.to_vec(), vec![3, 4, 5, 6]);
+        assert_eq!(ringbuffer.to_vec(), vec![3, 4, 5, 6]);
+
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![3, 4, 5, 6]);
+        assert!(ringbuffer.to_vec().is_empty());
+    }
+
+    #[test]
+    fn contains_position() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        assert!(!ringbuffer.contains(&1));
+        assert_eq!(ringbuffer.position(&1), None);
+
+        ringbuffer.n_write(&vec![1, 2, 3, 2]);
+        assert!(ringbuffer.contains(&2));
+        assert_eq!(ringbuffer.position(&2), Some(1));
+        assert_eq!(ringbuffer.position(&9), None);
+
+        // 8 wraps around to the start of the backing array
+        ringbuffer.drain(2);
+        ringbuffer.n_write(&vec![7, 8]);
+        assert_eq!(ringbuffer.position(&3), Some(0));
+        assert_eq!(ringbuffer.position(&8), Some(3));
+        assert_eq!(ringbuffer[3], 8);
+        // drained elements are gone even though their slots weren't overwritten
+        assert!(!ringbuffer.contains(&1));
+    }
+
+    #[test]
+    fn hash_by_contents() {
+        use std::collections::HashSet;
+
+        let mut at_start = RingBuffer::<i32, 4>::new();
+        at_start.n_write(&vec![2, 3, 4]);
+        let mut wrapped = RingBuffer::<i32, 4>::new();
+        wrapped.n_write(&vec![0, 1, 2, 3]);
+        wrapped.drain(2);
+        wrapped.n_write(&vec![4]);
+
+        let mut set = HashSet::new();
+        set.insert(at_start);
+        set.insert(wrapped.clone());
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(&wrapped));
+
+        wrapped.drain(1);
+        set.insert(wrapped);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn fill() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+
+        ringbuffer.n_write(&vec![1, 2, 3, 4]);
+        ringbuffer.drain(2);
+        assert_eq!(ringbuffer.fill(0), 3);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.fill(9), 0);
+
+        ringbuffer.n_read(&mut result);
+        assert_eq!(result, vec![3, 4, 0, 0, 0]);
+        assert_eq!(ringbuffer.fill(7), 5);
+        ringbuffer.n_read(&mut result);
+        assert_eq!(result, vec![7; 5]);
+    }
+
+    #[test]
+    fn read_append() {
+        let mut first = RingBuffer::<i32, 4>::new();
+        let mut second = RingBuffer::<i32, 4>::new();
+        first.n_write(&vec![1, 2, 3]);
+        second.n_write(&vec![0, 0, 0]);
+        second.drain(3);
+        second.n_write(&vec![4, 5, 6]);
+
+        let mut merged = vec![0];
+        assert_eq!(first.read_append(&mut merged), 3);
+        assert_eq!(second.read_append(&mut merged), 3);
+        assert_eq!(first.read_append(&mut merged), 0);
+        assert_eq!(merged, vec![0, 1, 2, 3, 4, 5, 6]);
+        assert!(first.is_empty() && second.is_empty());
+    }
+
+    #[test]
+    fn read_latest() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+
+        // fewer than max buffered, nothing is skipped
+        ringbuffer.n_write(&vec![1, 2, 3]);
+        assert_eq!(ringbuffer.read_latest(4, &mut result), 3);
+        assert_eq!(result, vec![1, 2, 3]);
+
+        // 4..=8 wrap around, only the newest 2 are read
+        ringbuffer.n_write(&vec![4, 5, 6, 7, 8]);
+        assert_eq!(ringbuffer.read_latest(2, &mut result), 2);
+        assert_eq!(result, vec![7, 8]);
+        assert!(ringbuffer.is_empty());
+
+        ringbuffer.n_write(&vec![9]);
+        assert_eq!(ringbuffer.read_latest(0, &mut result), 0);
+        assert!(result.is_empty());
+        assert!(ringbuffer.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        use crate::error::RingBufferError;
+
+        let mut ringbuffer = RingBuffer::<i32, 4>::new();
+        assert_eq!(ringbuffer.read_one(), Err(RingBufferError::Empty));
+        assert_eq!(
+            ringbuffer.write_exact(&[1, 2, 3, 4, 5]),
+            Err(RingBufferError::CapacityExceeded { requested: 5, capacity: 4 })
+        );
+        assert_eq!(ringbuffer.write_exact(&[1, 2, 3]), Ok(()));
+        assert_eq!(ringbuffer.write_exact(&[4, 5]), Err(RingBufferError::Full));
+        assert_eq!(ringbuffer.to_vec(), vec![1, 2, 3]);
+        assert_eq!(ringbuffer.dropped_count(), 0);
+
+        assert_eq!(ringbuffer.read_one(), Ok(1));
+        assert_eq!(ringbuffer.write_exact(&[4, 5]), Ok(()));
+        assert_eq!(ringbuffer.write_exact(&[4, 5, 6, 7]), Err(RingBufferError::Full));
+        assert_eq!(ringbuffer.to_vec(), vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn write_checked() {
+        use super::CapacityError;
+
+        let mut ringbuffer = RingBuffer::<i32, 4>::new();
+        assert_eq!(ringbuffer.write_checked(&[1, 2]), Ok(2));
+        assert_eq!(ringbuffer.drain(2), 2);
+        assert_eq!(ringbuffer.write_checked(&[1, 2, 3, 4]), Ok(4));
+
+        let err = ringbuffer.write_checked(&[1, 2, 3, 4, 5]).unwrap_err();
+        assert_eq!(err, CapacityError { requested: 5, capacity: 4 });
+        assert_eq!(err.to_string(), "cannot write 5 elements into a ring buffer of capacity 4");
+        assert_eq!(ringbuffer.to_vec(), vec![1, 2, 3, 4]);
+
+        // fits the capacity but not the free space, that's just a short write
+        ringbuffer.drain(1);
+        assert_eq!(ringbuffer.write_checked(&[5, 6]), Ok(1));
+    }
+
+    #[test]
+    fn free() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut out = [0; 2];
+        assert_eq!(ringbuffer.free(), 5);
+        ringbuffer.write_from(&[1, 2, 3]);
+        assert_eq!(ringbuffer.free(), 2);
+        ringbuffer.read_into(&mut out);
+        assert_eq!(ringbuffer.free(), 4);
+        ringbuffer.write_from(&[4, 5, 6, 7, 8]);
+        assert_eq!(ringbuffer.free(), 0);
+    }
+
+    #[test]
+    fn write_chunked() {
+        let mut ringbuffer = RingBuffer::<u32, 8>::new();
+        let data: Vec<u32> = (0..100).collect();
+        let mut received = Vec::new();
+        let mut out = [0; 5];
+        let mut progress = Vec::new();
+
+        let mut done = 0;
+        while done < data.len() {
+            done += ringbuffer.write_chunked(&data[done..], |n| progress.push(n));
+            let n = ringbuffer.read_into(&mut out);
+            received.extend_from_slice(&out[..n]);
+        }
+        while !ringbuffer.is_empty() {
+            let n = ringbuffer.read_into(&mut out);
+            received.extend_from_slice(&out[..n]);
+        }
+        assert_eq!(received, data);
+        assert_eq!(ringbuffer.dropped_count(), 0);
+
+        // 3 free slots at the end and 2 at the start are two chunks
+        let mut ringbuffer = RingBuffer::<u32, 5>::new();
+        ringbuffer.write_from(&[0, 0]);
+        ringbuffer.drain(2);
+        progress.clear();
+        assert_eq!(ringbuffer.write_chunked(&data, |n| progress.push(n)), 5);
+        assert_eq!(progress, vec![3, 5]);
+        assert_eq!(ringbuffer.write_chunked(&data, |n| progress.push(n)), 0);
+        assert_eq!(progress, vec![3, 5]);
+    }
+
+    #[test]
+    fn from_iter() {
+        let mut ringbuffer: RingBuffer<i32, 8> = (0..5).collect();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.len(), 5);
+        assert_eq!(ringbuffer.n_read(&mut result), 5);
+        assert_eq!(result, vec![0, 1, 2, 3, 4]);
+
+        // more items than capacity keeps the first N, and the buffer still wraps normally
+        let mut items = 0..;
+        let mut ringbuffer: RingBuffer<i32, 4> = items.by_ref().collect();
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.dropped_count(), 0);
+        assert_eq!(items.next(), Some(4));
+        ringbuffer.drain(1);
+        assert_eq!(ringbuffer.write_from(&[9]), 1);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![1, 2, 3, 9]);
+
+        let ringbuffer: RingBuffer<i32, 4> = core::iter::empty().collect();
+        assert!(ringbuffer.is_empty());
+    }
+
+    #[test]
+    fn extend() {
+        let mut ringbuffer = RingBuffer::<i32, 4>::new();
+        let mut result = Vec::new();
+        ringbuffer.extend([1, 2]);
+        assert_eq!(ringbuffer.len(), 2);
+        assert_eq!(ringbuffer.dropped_count(), 0);
+
+        // past capacity only the newest 4 remain
+        ringbuffer.extend(3..10);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.dropped_count(), 5);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![6, 7, 8, 9]);
+
+        // and the buffer keeps working from wherever the extend left it
+        ringbuffer.extend([10]);
+        assert_eq!(ringbuffer.write_from(&[11, 12, 13, 14]), 3);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn transfer_to() {
+        let mut src = RingBuffer::<i32, 8>::new();
+        let mut small = RingBuffer::<i32, 3>::new();
+        let mut large = RingBuffer::<i32, 16>::new();
+        let mut result = Vec::new();
+
+        // wrapped source, partial transfer once the destination fills
+        src.write_from(&[0; 6]);
+        src.drain(6);
+        src.write_from(&[1, 2, 3, 4, 5]);
+        small.write_from(&[0]);
+        assert_eq!(src.transfer_to(&mut small), 2);
+        assert_eq!(src.len(), 3);
+        assert_eq!(src.transfer_to(&mut small), 0);
+        assert_eq!(small.n_read(&mut result), 3);
+        assert_eq!(result, vec![0, 1, 2]);
+
+        // the rest fits
+        assert_eq!(src.transfer_to(&mut large), 3);
+        assert!(src.is_empty());
+        assert_eq!(large.n_read(&mut result), 3);
+        assert_eq!(result, vec![3, 4, 5]);
+        assert_eq!(src.dropped_count() + small.dropped_count() + large.dropped_count(), 0);
+    }
+
+    #[test]
+    fn default() {
+        #[derive(Default)]
+        struct Channel {
+            ringbuffer: RingBuffer<u8, 4>,
+            sent: usize,
+        }
+
+        let mut channel = Channel::default();
+        let mut result = Vec::new();
+        assert!(channel.ringbuffer.is_empty());
+        channel.sent += channel.ringbuffer.write_from(b"hello");
+        assert_eq!(channel.sent, 4);
+        assert_eq!(channel.ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, b"hell");
+    }
+
+    #[test]
+    fn peek_back() {
+        let mut ringbuffer = RingBuffer::<i32, 3>::new();
+        assert_eq!(ringbuffer.peek_back(), None);
+
+        ringbuffer.write_from(&[1, 2]);
+        assert_eq!(ringbuffer.peek_back(), Some(&2));
+        // tail wraps to 0, the newest element sits in slot N - 1
+        ringbuffer.write_from(&[3]);
+        assert_eq!(ringbuffer.peek_back(), Some(&3));
+        ringbuffer.drain(2);
+        ringbuffer.write_from(&[4]);
+        assert_eq!(ringbuffer.peek_back(), Some(&4));
+        assert_eq!(ringbuffer.peek(), Some(&3));
+
+        ringbuffer.drain(2);
+        assert_eq!(ringbuffer.peek_back(), None);
+    }
+
+    #[test]
+    fn retain() {
+        let mut ringbuffer = RingBuffer::<i32, 6>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[0; 4]);
+        ringbuffer.drain(4);
+        ringbuffer.write_from(&[1, 2, 3, 4, 5, 6]);
+        assert_ne!(ringbuffer.as_slices().1.len(), 0);
+
+        ringbuffer.retain(|elem| elem % 2 == 0);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+        assert_eq!(ringbuffer.len(), 3);
+        assert_eq!(ringbuffer.dropped_count(), 0);
+        assert_eq!(ringbuffer.peek_back(), Some(&6));
+
+        // the freed slots are writable again
+        assert_eq!(ringbuffer.write_from(&[8, 10, 12, 14]), 3);
+        assert_eq!(ringbuffer.n_read(&mut result), 6);
+        assert_eq!(result, vec![2, 4, 6, 8, 10, 12]);
+
+        ringbuffer.write_from(&[1, 3]);
+        ringbuffer.retain(|_| false);
+        assert!(ringbuffer.is_empty());
+    }
+
+    #[test]
+    fn try_from_vec() {
+        let mut result = Vec::new();
+        let mut ringbuffer = RingBuffer::<i32, 4>::try_from(vec![1, 2, 3]).unwrap();
+        assert_eq!(ringbuffer.free(), 1);
+        assert_eq!(ringbuffer.n_read(&mut result), 3);
+        assert_eq!(result, vec![1, 2, 3]);
+
+        let ringbuffer = RingBuffer::<i32, 4>::try_from(vec![1, 2, 3, 4]).unwrap();
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.to_vec(), vec![1, 2, 3, 4]);
+
+        let too_long = vec![1, 2, 3, 4, 5];
+        let ptr = too_long.as_ptr();
+        let err = RingBuffer::<i32, 4>::try_from(too_long).unwrap_err();
+        assert_eq!(err, vec![1, 2, 3, 4, 5]);
+        assert_eq!(err.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn reverse() {
+        let mut ringbuffer = RingBuffer::<i32, 6>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[0; 4]);
+        ringbuffer.drain(4);
+        ringbuffer.write_from(&[1, 2, 3, 4, 5]);
+        assert_ne!(ringbuffer.as_slices().1.len(), 0);
+
+        ringbuffer.reverse();
+        assert_eq!(ringbuffer.n_read(&mut result), 5);
+        assert_eq!(result, vec![5, 4, 3, 2, 1]);
+
+        // even length, and nothing to do for one or no element
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+        ringbuffer.reverse();
+        assert_eq!(ringbuffer.to_vec(), vec![4, 3, 2, 1]);
+        ringbuffer.drain(3);
+        ringbuffer.reverse();
+        assert_eq!(ringbuffer.to_vec(), vec![1]);
+        ringbuffer.drain(1);
+        ringbuffer.reverse();
+        assert!(ringbuffer.is_empty());
+    }
+
+    #[test]
+    fn replace() {
+        let mut ringbuffer = RingBuffer::<i32, 4>::new();
+        assert_eq!(ringbuffer.replace(0, 9), None);
+        ringbuffer.write_from(&[0; 3]);
+        ringbuffer.drain(3);
+        ringbuffer.write_from(&[1, 2, 3]);
+
+        // first, middle and last, the last two sit past the wrap
+        assert_eq!(ringbuffer.replace(0, 10), Some(1));
+        assert_eq!(ringbuffer.replace(1, 20), Some(2));
+        assert_eq!(ringbuffer.replace(2, 30), Some(3));
+        assert_eq!(ringbuffer.replace(3, 40), None);
+        assert_eq!(ringbuffer.len(), 3);
+        assert_eq!(ringbuffer.to_vec(), vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn for_each_mut() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[0; 3]);
+        ringbuffer.drain(3);
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+
+        let mut visited = 0;
+        ringbuffer.for_each_mut(|elem| {
+            *elem *= 2;
+            visited += 1;
+        });
+        assert_eq!(visited, 4);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![2, 4, 6, 8]);
+
+        ringbuffer.for_each_mut(|_| panic!("nothing is buffered"));
+    }
+
+    #[test]
+    fn saturating_write() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        assert_eq!(ringbuffer.saturating_write(&[1, 2, 3]), (3, 0));
+        assert_eq!(ringbuffer.saturating_write(&[4, 5, 6, 7]), (2, 2));
+        assert_eq!(ringbuffer.saturating_write(&[8, 9]), (0, 2));
+        assert_eq!(ringbuffer.saturating_write(&[]), (0, 0));
+        assert_eq!(ringbuffer.dropped_count(), 4);
+        assert_eq!(ringbuffer.to_vec(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn random_operations_keep_invariants() {
+        use std::collections::VecDeque;
+
+        let mut ringbuffer = RingBuffer::<u32, 7>::new();
+        let mut model = VecDeque::new();
+        let mut out = [0; 10];
+        // xorshift, deterministic so a failure can be replayed
+        let mut state = 0x2545_f491_u32;
+        let mut random = move |bound: u32| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state % bound
+        };
+
+        for step in 0..10_000 {
+            let n = random(10) as usize;
+            let data: Vec<u32> = (0..n as u32).map(|i| step * 10 + i).collect();
+            match random(7) {
+                0 | 1 => {
+                    let written = ringbuffer.write_from(&data);
+                    model.extend(&data[..written]);
+                }
+                2 => {
+                    ringbuffer.n_write_overwrite(&data);
+                    model.extend(&data);
+                    model.drain(..model.len().saturating_sub(7));
+                }
+                3 => {
+                    let read = ringbuffer.read_into(&mut out[..n]);
+                    let expected: Vec<u32> = model.drain(..read).collect();
+                    assert_eq!(out[..read], expected[..]);
+                }
+                4 => {
+                    let drained = ringbuffer.drain(n);
+                    model.drain(..drained);
+                }
+                5 => {
+                    ringbuffer.extend(data.iter().copied());
+                    model.extend(&data);
+                    model.drain(..model.len().saturating_sub(7));
+                }
+                _ => {
+                    ringbuffer.retain(|elem| elem % 3 != 0);
+                    model.retain(|elem| elem % 3 != 0);
+                }
+            }
+            assert_eq!(ringbuffer.check_invariants(), Ok(()), "after step {}", step);
+            assert!(ringbuffer.iter().eq(model.iter()), "after step {}", step);
+        }
+    }
+
+    #[test]
+    fn write_from_iter() {
+        let mut ringbuffer = RingBuffer::<u32, 6>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[0; 4]);
+        ringbuffer.drain(3);
+
+        // an endless iterator, exactly the free slots are taken and it picks up after them
+        let mut naturals = 1..;
+        let free = ringbuffer.free();
+        assert_eq!(ringbuffer.write_from_iter(naturals.by_ref()), free);
+        assert_eq!(naturals.next(), Some(free as u32 + 1));
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.write_from_iter(naturals.by_ref()), 0);
+        assert_eq!(ringbuffer.n_read(&mut result), 6);
+        assert_eq!(result, vec![0, 1, 2, 3, 4, 5]);
+
+        // runs out first
+        assert_eq!(ringbuffer.write_from_iter([7, 8].into_iter()), 2);
+        assert_eq!(ringbuffer.n_read(&mut result), 2);
+        assert_eq!(result, vec![7, 8]);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn reserve_commit() {
+        let mut ringbuffer = RingBuffer::<u8, 8>::new();
+        let mut result = Vec::new();
+
+        let slots = ringbuffer.reserve(5);
+        assert_eq!(slots.len(), 5);
+        slots[..3].copy_from_slice(b"abc");
+        ringbuffer.commit(3);
+        assert_eq!(ringbuffer.len(), 3);
+
+        // only the contiguous run up to the end of the array
+        ringbuffer.drain(3);
+        let slots = ringbuffer.reserve(8);
+        assert_eq!(slots.len(), 5);
+        slots.copy_from_slice(b"defgh");
+        ringbuffer.commit(5);
+        let slots = ringbuffer.reserve(8);
+        assert_eq!(slots.len(), 3);
+        slots.copy_from_slice(b"ijk");
+        ringbuffer.commit(3);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.reserve(1).len(), 0);
+        ringbuffer.commit(0);
+
+        assert_eq!(ringbuffer.n_read(&mut result), 8);
+        assert_eq!(result, b"defghijk");
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "only 2 are reserved")]
+    fn commit_more_than_reserved() {
+        let mut ringbuffer = RingBuffer::<u8, 8>::new();
+        ringbuffer.reserve(2);
+        ringbuffer.commit(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "only 0 are reserved")]
+    fn commit_after_write() {
+        let mut ringbuffer = RingBuffer::<u8, 8>::new();
+        ringbuffer.reserve(4);
+        ringbuffer.write_from(&[1]);
+        ringbuffer.commit(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "only 0 are reserved")]
+    fn commit_after_full_lap() {
+        let mut ringbuffer = RingBuffer::<u8, 8>::new();
+        ringbuffer.reserve(4);
+        // tail ends up where it was
+        ringbuffer.extend(0..8);
+        ringbuffer.commit(1);
+    }
+
+    #[test]
+    fn drain_all() {
+        let mut ringbuffer = RingBuffer::<i32, 5>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[1, 2, 3]);
+        ringbuffer.drain(2);
+        ringbuffer.write_from(&[4, 5, 6]);
+
+        assert_eq!(ringbuffer.drain_all(&mut result), 4);
+        assert_eq!(result, vec![3, 4, 5, 6]);
+        assert_eq!(ringbuffer.head, 0);
+        assert_eq!(ringbuffer.tail, 0);
+
+        // the next write lands at index 0 and doesn't wrap
+        ringbuffer.write_from(&[7, 8, 9, 10, 11]);
+        assert_eq!(ringbuffer.as_slices(), (&[7, 8, 9, 10, 11][..], &[][..]));
+        assert_eq!(ringbuffer.drain_all(&mut result), 5);
+        assert_eq!(ringbuffer.drain_all(&mut result), 0);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut ringbuffer = RingBuffer::<u8, 8>::new();
+        ringbuffer.write_from(b"xxxxx");
+        ringbuffer.drain(5);
+        ringbuffer.write_from(b"packet");
+        assert_ne!(ringbuffer.as_slices().1.len(), 0);
+
+        let bytes = ringbuffer.to_bytes();
+        assert_eq!(bytes, b"packet");
+        let decoded = RingBuffer::<u8, 8>::from_bytes(&bytes);
+        assert_eq!(decoded, ringbuffer);
+        assert_eq!(decoded.as_slices(), (&b"packet"[..], &[][..]));
+
+        // more than fits keeps the first N
+        assert_eq!(RingBuffer::<u8, 4>::from_bytes(&bytes).to_bytes(), b"pack");
+    }
+
+    #[test]
+    fn count() {
+        let mut ringbuffer = RingBuffer::<f64, 6>::new();
+        assert_eq!(ringbuffer.count(|_| true), 0);
+        ringbuffer.write_from(&[9.0; 4]);
+        ringbuffer.drain(4);
+        ringbuffer.write_from(&[0.5, 1.5, 0.2, 3.0, 2.5]);
+
+        let state = |ringbuffer: &RingBuffer<f64, 6>| {
+            (
+                ringbuffer.head,
+                ringbuffer.tail,
+                ringbuffer.used_count,
+            )
+        };
+        let before = state(&ringbuffer);
+        assert_eq!(ringbuffer.count(|&sample| sample > 1.0), 3);
+        assert_eq!(ringbuffer.count(|&sample| sample > 5.0), 0);
+        assert_eq!(state(&ringbuffer), before);
+        assert_eq!(ringbuffer.len(), 5);
+    }
+
+    #[test]
+    fn first_last() {
+        let mut ringbuffer = RingBuffer::<i32, 4>::new();
+        assert_eq!((ringbuffer.first(), ringbuffer.last()), (None, None));
+
+        ringbuffer.write_from(&[1]);
+        assert_eq!((ringbuffer.first(), ringbuffer.last()), (Some(1), Some(1)));
+
+        ringbuffer.write_from(&[2, 3, 4]);
+        ringbuffer.drain(3);
+        ringbuffer.write_from(&[5, 6]);
+        assert_eq!((ringbuffer.first(), ringbuffer.last()), (Some(4), Some(6)));
+    }
+
+    #[test]
+    fn sum_mean() {
+        let mut window = RingBuffer::<f64, 4>::new();
+        assert_eq!(window.sum(), 0.0);
+        assert_eq!(window.mean(), None);
+
+        window.write_from(&[1.0, 2.0, 3.0]);
+        assert_eq!(window.sum(), 6.0);
+        assert_eq!(window.mean(), Some(2.0));
+
+        // a moving window over 1..=6, the last 4 after a wrap
+        window.extend([4.0, 5.0, 6.0]);
+        assert_ne!(window.as_slices().1.len(), 0);
+        assert_eq!(window.sum(), 18.0);
+        assert_eq!(window.mean(), Some(4.5));
+
+        let mut integers = RingBuffer::<u32, 3>::from_slice(&[7, 8]);
+        assert_eq!(integers.sum(), 15);
+        integers.drain(2);
+        assert_eq!(integers.sum(), 0);
+    }
+
+    #[test]
+    fn push_sink() {
+        use super::PushSink;
+
+        let mut sink: Box<dyn PushSink<i32>> = Box::new(RingBuffer::<i32, 4>::new());
+        let mut pushed = 0;
+        while sink.free() > 0 {
+            assert_eq!(sink.try_push(pushed), Ok(()));
+            pushed += 1;
+        }
+        assert_eq!(pushed, 4);
+        assert_eq!(sink.try_push(9), Err(9));
+    }
+
+    #[test]
+    fn move_between_threads() {
+        let mut ringbuffer = RingBuffer::<u32, 4>::new();
+        ringbuffer.write_from(&[1, 2]);
+
+        // single threaded, but it can be handed from one thread to the next
+        let mut ringbuffer = std::thread::spawn(move || {
+            let mut out = [0; 1];
+            assert_eq!(ringbuffer.read_into(&mut out), 1);
+            assert_eq!(ringbuffer.write_from(&[3, 4, 5]), 3);
+            ringbuffer
+        })
+        .join()
+        .unwrap();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![2, 3, 4, 5]);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn min_max() {
+        let mut ringbuffer = RingBuffer::<f64, 6>::new();
+        assert_eq!((ringbuffer.min(), ringbuffer.max()), (None, None));
+
+        ringbuffer.write_from(&[0.0; 4]);
+        ringbuffer.drain(4);
+        // the minimum before the wrap, the maximum after it
+        ringbuffer.write_from(&[2.5, -1.0, 3.0, 7.5, 0.5, 4.0]);
+        assert_eq!(ringbuffer.min(), Some(-1.0));
+        assert_eq!(ringbuffer.max(), Some(7.5));
+
+        ringbuffer.drain(5);
+        assert_eq!((ringbuffer.min(), ringbuffer.max()), (Some(4.0), Some(4.0)));
+    }
+
+    #[test]
+    fn read_array() {
+        let mut ringbuffer = RingBuffer::<u8, 6>::new();
+        ringbuffer.write_from(&[0; 4]);
+        ringbuffer.drain(4);
+        ringbuffer.write_from(&[1, 2, 3, 4, 5]);
+
+        // fewer than buffered, across the wrap
+        assert_eq!(ringbuffer.read_array::<3>(), Some([1, 2, 3]));
+        // more than buffered leaves everything in place
+        assert_eq!(ringbuffer.read_array::<3>(), None);
+        assert_eq!(ringbuffer.len(), 2);
+        // exactly what is buffered
+        assert_eq!(ringbuffer.read_array::<2>(), Some([4, 5]));
+        assert!(ringbuffer.is_empty());
+        assert_eq!(ringbuffer.read_array::<0>(), Some([]));
+    }
+
+    #[test]
+    fn write_vectored() {
+        let mut ringbuffer = RingBuffer::<u8, 8>::new();
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[0; 5]);
+        ringbuffer.drain(5);
+
+        // the second fragment wraps, the third only partly fits
+        let header: &[u8] = b"hd";
+        let body: &[u8] = b"body";
+        let trailer: &[u8] = b"trailer";
+        assert_eq!(ringbuffer.write_vectored(&[header, body, trailer]), 8);
+        assert_eq!(ringbuffer.dropped_count(), 5);
+        assert_eq!(ringbuffer.n_read(&mut result), 8);
+        assert_eq!(result, b"hdbodytr");
+        assert_eq!(ringbuffer.write_vectored(&[]), 0);
+    }
+
+}
+
+/// only the slice based API, so this also runs without `alloc`
+#[cfg(test)]
+mod slice_test {
+    use super::RingBuffer;
+
+    #[test]
+    fn write_read_slices() {
+        let mut ringbuffer = RingBuffer::<u16, 4>::from_slice(&[1, 2, 3]);
+        let mut out = [0; 4];
+
+        assert_eq!(ringbuffer.read_into(&mut out[..2]), 2);
+        assert_eq!(out[..2], [1, 2]);
+        // wraps around
+        assert_eq!(ringbuffer.write_from(&[4, 5, 6, 7]), 3);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.read_into(&mut out), 4);
+        assert_eq!(out, [3, 4, 5, 6]);
+        assert_eq!(ringbuffer.read_into(&mut out), 0);
+    }
 }
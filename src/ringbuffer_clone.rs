@@ -0,0 +1,189 @@
+//! generic ring buffer for types that are only Clone, e.g. `String`
+//! elements are moved in by `push` or cloned in by `write_from`, reads move them back out,
+//! so nothing is cloned on the way out
+
+use core::cmp::min;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+
+/// the slots of the live region `head..head + used_count` (with wraparound) are `Some`,
+/// every other slot is `None`, reading a slot `take`s the element out of it
+pub struct CloneRingBuffer<T, const N: usize> {
+    buffer: [Option<T>; N],
+    head: usize,
+    tail: usize,
+    used_count: usize,
+}
+
+impl<T, const N: usize> CloneRingBuffer<T, N>
+where
+    T: Clone
+{
+    /// a capacity of 0 fails to compile, there would be no slot to index
+    const NON_ZERO: () = assert!(N > 0, "capacity of CloneRingBuffer must be non-zero");
+
+    pub fn new() -> Self {
+        let () = Self::NON_ZERO;
+        Self {
+            buffer: [(); N].map(|_| None),
+            head: 0,
+            tail: 0,
+            used_count: 0,
+        }
+    }
+
+    /// move `elem` in, handing it back when the buffer is full
+    pub fn push(&mut self, elem: T) -> Result<(), T> {
+        if self.used_count == N {
+            return Err(elem);
+        }
+        self.buffer[self.tail] = Some(elem);
+        self.tail = (self.tail + 1) % N;
+        self.used_count += 1;
+        Ok(())
+    }
+    /// clone as many elements of `data` in as there is free space for,
+    /// returns the number of elements actually written
+    pub fn write_from(&mut self, data: &[T]) -> usize {
+        let write_count = min(data.len(), N - self.used_count);
+        for elem in &data[..write_count] {
+            self.buffer[self.tail] = Some(elem.clone());
+            self.tail = (self.tail + 1) % N;
+        }
+        self.used_count += write_count;
+        write_count
+    }
+    /// move the oldest element out, if any
+    pub fn pop(&mut self) -> Option<T> {
+        let elem = self.buffer[self.head].take()?;
+        self.head = (self.head + 1) % N;
+        self.used_count -= 1;
+        Some(elem)
+    }
+    /// clear `data` and move every buffered element into it,
+    /// returns the number of elements actually read
+    #[cfg(feature = "alloc")]
+    pub fn n_read(&mut self, data: &mut Vec<T>) -> usize {
+        data.clear();
+        data.extend(core::iter::from_fn(|| self.pop()));
+        data.len()
+    }
+
+    /// number of elements currently buffered
+    pub fn len(&self) -> usize {
+        self.used_count
+    }
+    pub fn is_empty(&self) -> bool {
+        self.used_count == 0
+    }
+    pub fn is_full(&self) -> bool {
+        self.used_count == N
+    }
+    /// number of elements that can be written before the buffer is full
+    pub fn free(&self) -> usize {
+        N - self.used_count
+    }
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T: Clone, const N: usize> Default for CloneRingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Clone, const N: usize> crate::RingBuffer<T> for CloneRingBuffer<T, N> {
+    fn write(&mut self, data: &[T]) -> usize {
+        self.write_from(data)
+    }
+    fn read(&mut self, data: &mut Vec<T>) -> usize {
+        self.n_read(data)
+    }
+    fn len(&self) -> usize {
+        self.used_count
+    }
+    fn capacity(&self) -> usize {
+        N
+    }
+}
+
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::CloneRingBuffer;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// a `String` that counts how often it has been cloned
+    struct Tracked {
+        value: String,
+        clones: Rc<Cell<usize>>,
+    }
+
+    impl Clone for Tracked {
+        fn clone(&self) -> Self {
+            self.clones.set(self.clones.get() + 1);
+            Self { value: self.value.clone(), clones: Rc::clone(&self.clones) }
+        }
+    }
+
+    #[test]
+    fn strings() {
+        let mut ringbuffer = CloneRingBuffer::<String, 3>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.capacity(), 3);
+
+        assert_eq!(ringbuffer.push("a".to_string()), Ok(()));
+        assert_eq!(ringbuffer.write_from(&["b".to_string(), "c".to_string(), "d".to_string()]), 2);
+        assert_eq!(ringbuffer.push("e".to_string()), Err("e".to_string()));
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.pop().as_deref(), Some("a"));
+
+        // wraps around
+        assert_eq!(ringbuffer.push("d".to_string()), Ok(()));
+        assert_eq!(ringbuffer.n_read(&mut result), 3);
+        assert_eq!(result, ["b", "c", "d"]);
+        assert_eq!(ringbuffer.pop(), None);
+        assert_eq!(ringbuffer.n_read(&mut result), 0);
+    }
+
+    #[test]
+    fn no_clone_on_read() {
+        let clones = Rc::new(Cell::new(0));
+        let tracked = |value: &str| Tracked { value: value.to_string(), clones: Rc::clone(&clones) };
+        let mut ringbuffer = CloneRingBuffer::<Tracked, 4>::new();
+        let mut result = Vec::new();
+
+        for round in 0..3 {
+            for i in 0..3 {
+                assert!(ringbuffer.push(tracked(&format!("{}-{}", round, i))).is_ok());
+            }
+            assert_eq!(ringbuffer.pop().unwrap().value, format!("{}-0", round));
+            assert_eq!(ringbuffer.n_read(&mut result), 2);
+            assert_eq!(result[1].value, format!("{}-2", round));
+        }
+        assert_eq!(clones.get(), 0);
+
+        // only writing from a borrowed slice clones
+        ringbuffer.write_from(&[tracked("x"), tracked("y")]);
+        assert_eq!(clones.get(), 2);
+        ringbuffer.n_read(&mut result);
+        assert_eq!(clones.get(), 2);
+    }
+
+    #[test]
+    fn drop_unread() {
+        let value = Rc::new(());
+        {
+            let mut ringbuffer = CloneRingBuffer::<Rc<()>, 2>::new();
+            ringbuffer.push(Rc::clone(&value)).unwrap();
+            ringbuffer.push(Rc::clone(&value)).unwrap();
+            assert_eq!(Rc::strong_count(&value), 3);
+        }
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+}
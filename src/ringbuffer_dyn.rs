@@ -0,0 +1,239 @@
+//! generic ring buffer whose capacity is chosen at runtime
+//! the type T must implement Copy and Default trait, the backing slice starts out filled
+//! with `T::default()`
+
+use core::cmp::min;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+
+/// same layout as the const generic `RingBuffer`, `head`/`tail` are slot indices and
+/// `used_count` tells a full buffer from an empty one
+pub struct DynRingBuffer<T> {
+    buffer: Box<[T]>,
+    head: usize,
+    tail: usize,
+    used_count: usize,
+}
+
+impl<T> DynRingBuffer<T>
+where
+    T: Copy + Default
+{
+    /// a buffer holding at most `cap` elements, panics if `cap` is 0
+    pub fn new(cap: usize) -> Self {
+        assert!(cap > 0, "capacity of DynRingBuffer must be non-zero");
+        Self {
+            buffer: vec![T::default(); cap].into_boxed_slice(),
+            head: 0,
+            tail: 0,
+            used_count: 0,
+        }
+    }
+
+    /// write as many elements of `data` as there is free space for,
+    /// returns the number of elements actually written
+    #[allow(clippy::ptr_arg)]
+    pub fn n_write(&mut self, data: &Vec<T>) -> usize {
+        self.write_from(data)
+    }
+    /// same as `n_write` but takes any slice
+    pub fn write_from(&mut self, data: &[T]) -> usize {
+        let cap = self.capacity();
+        let write_count = min(data.len(), cap - self.used_count);
+        let (front, back) = data[..write_count].split_at(min(write_count, cap - self.tail));
+        self.buffer[self.tail..self.tail + front.len()].copy_from_slice(front);
+        self.buffer[..back.len()].copy_from_slice(back);
+
+        self.used_count += write_count;
+        self.tail = (self.tail + write_count) % cap;
+        write_count
+    }
+    /// clear `data` and move every buffered element into it,
+    /// returns the number of elements actually read
+    pub fn n_read(&mut self, data: &mut Vec<T>) -> usize {
+        data.clear();
+        let (front, back) = self.as_slices();
+        data.extend_from_slice(front);
+        data.extend_from_slice(back);
+        self.drain(data.len())
+    }
+    /// copy at most `out.len()` buffered elements into `out` without allocating,
+    /// returns the number of elements actually read
+    pub fn read_into(&mut self, out: &mut [T]) -> usize {
+        let read_count = min(out.len(), self.used_count);
+        let (front, back) = self.as_slices();
+        let front_count = min(read_count, front.len());
+        out[..front_count].copy_from_slice(&front[..front_count]);
+        out[front_count..read_count].copy_from_slice(&back[..read_count - front_count]);
+        self.drain(read_count)
+    }
+    /// discard at most `n` of the oldest buffered elements,
+    /// returns the number of elements actually skipped
+    pub fn drain(&mut self, n: usize) -> usize {
+        let drain_count = min(n, self.used_count);
+        self.used_count -= drain_count;
+        self.head = (self.head + drain_count) % self.capacity();
+        drain_count
+    }
+
+    /// number of elements currently buffered
+    pub fn len(&self) -> usize {
+        self.used_count
+    }
+    pub fn is_empty(&self) -> bool {
+        self.used_count == 0
+    }
+    pub fn is_full(&self) -> bool {
+        self.used_count == self.capacity()
+    }
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+    /// move to a backing slice of `new_cap` slots, the buffered elements are copied over
+    /// in FIFO order starting at index 0. When they don't all fit the oldest ones are
+    /// dropped, like an overwriting write would. Panics if `new_cap` is 0.
+    pub fn resize(&mut self, new_cap: usize) {
+        assert!(new_cap > 0, "capacity of DynRingBuffer must be non-zero");
+        let mut buffer = vec![T::default(); new_cap].into_boxed_slice();
+        let keep = min(self.used_count, new_cap);
+        self.drain(self.used_count - keep);
+        let (front, back) = self.as_slices();
+        buffer[..front.len()].copy_from_slice(front);
+        buffer[front.len()..keep].copy_from_slice(back);
+
+        self.buffer = buffer;
+        self.head = 0;
+        self.tail = keep % new_cap;
+        self.used_count = keep;
+    }
+
+    /// the buffered elements as two contiguous slices in FIFO order, the second one is
+    /// empty when the data doesn't wrap
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let cap = self.capacity();
+        if self.used_count <= cap - self.head {
+            (&self.buffer[self.head..self.head + self.used_count], &self.buffer[..0])
+        } else {
+            (&self.buffer[self.head..], &self.buffer[..self.used_count - (cap - self.head)])
+        }
+    }
+}
+
+impl<T: Copy + Default> crate::RingBuffer<T> for DynRingBuffer<T> {
+    fn write(&mut self, data: &[T]) -> usize {
+        self.write_from(data)
+    }
+    fn read(&mut self, data: &mut Vec<T>) -> usize {
+        self.n_read(data)
+    }
+    fn len(&self) -> usize {
+        self.used_count
+    }
+    fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::DynRingBuffer;
+    use crate::ringbuffer::RingBuffer;
+
+    #[test]
+    fn basics() {
+        let mut ringbuffer = DynRingBuffer::<i32>::new(5);
+        let mut result = Vec::new();
+        let mut out = [0; 3];
+        assert_eq!(ringbuffer.capacity(), 5);
+        assert!(ringbuffer.is_empty());
+
+        assert_eq!(ringbuffer.n_write(&vec![1, 2, 3, 4]), 4);
+        assert_eq!(ringbuffer.read_into(&mut out), 3);
+        assert_eq!(out, [1, 2, 3]);
+
+        // wraps around the end of the backing slice
+        assert_eq!(ringbuffer.write_from(&[5, 6, 7, 8, 9]), 4);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.as_slices(), (&[4, 5][..], &[6, 7, 8][..]));
+        assert_eq!(ringbuffer.n_read(&mut result), 5);
+        assert_eq!(result, vec![4, 5, 6, 7, 8]);
+        assert_eq!(ringbuffer.n_read(&mut result), 0);
+
+        let mut one = DynRingBuffer::<i32>::new(1);
+        assert_eq!(one.write_from(&[1, 2]), 1);
+        assert_eq!(one.n_read(&mut result), 1);
+        assert_eq!(one.write_from(&[3]), 1);
+        assert_eq!(one.n_read(&mut result), 1);
+        assert_eq!(result, vec![3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "non-zero")]
+    fn zero_capacity() {
+        DynRingBuffer::<i32>::new(0);
+    }
+
+    /// runs the same writes and reads against a const generic buffer of capacity `N`
+    fn same_as_generic<const N: usize>() {
+        let mut dynamic = DynRingBuffer::<u32>::new(N);
+        let mut generic = RingBuffer::<u32, N>::new();
+        let mut dynamic_result = Vec::new();
+        let mut generic_result = Vec::new();
+
+        let mut next = 0;
+        for round in 0..500 {
+            let data: Vec<u32> = (next..next + (round * 7 % 13) as u32).collect();
+            next += data.len() as u32;
+            assert_eq!(dynamic.n_write(&data), generic.n_write(&data));
+            assert_eq!(dynamic.len(), generic.len());
+            assert_eq!(dynamic.as_slices(), generic.as_slices());
+            if round % 3 != 0 {
+                assert_eq!(dynamic.n_read(&mut dynamic_result), generic.n_read(&mut generic_result));
+                assert_eq!(dynamic_result, generic_result);
+            }
+        }
+    }
+
+    #[test]
+    fn runtime_capacities() {
+        same_as_generic::<3>();
+        same_as_generic::<10>();
+        same_as_generic::<16>();
+    }
+
+    #[test]
+    fn resize() {
+        let mut ringbuffer = DynRingBuffer::<i32>::new(4);
+        let mut result = Vec::new();
+        ringbuffer.write_from(&[1, 2, 3, 4]);
+        ringbuffer.drain(2);
+        ringbuffer.write_from(&[5]);
+        assert_ne!(ringbuffer.as_slices().1.len(), 0);
+
+        // growing keeps everything, unwrapped
+        ringbuffer.resize(6);
+        assert_eq!(ringbuffer.capacity(), 6);
+        assert_eq!(ringbuffer.as_slices(), (&[3, 4, 5][..], &[][..]));
+        assert_eq!(ringbuffer.write_from(&[6, 7, 8, 9]), 3);
+        assert!(ringbuffer.is_full());
+
+        // shrinking below the length keeps the newest
+        ringbuffer.drain(1);
+        ringbuffer.write_from(&[9]);
+        ringbuffer.resize(3);
+        assert_eq!(ringbuffer.capacity(), 3);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.n_read(&mut result), 3);
+        assert_eq!(result, vec![7, 8, 9]);
+
+        // shrinking an empty buffer and writing on
+        ringbuffer.resize(2);
+        assert_eq!(ringbuffer.write_from(&[1, 2, 3]), 2);
+        assert_eq!(ringbuffer.n_read(&mut result), 2);
+        assert_eq!(result, vec![1, 2]);
+    }
+
+}
@@ -0,0 +1,280 @@
+//! thread safe ring buffer for many producers and a single consumer
+//! the elements are `usize`, `push` takes `&self` while `pop` and `n_read` take `&mut self`,
+//! to use it from several threads `split` it into an `MpscProducer`, which can be cloned,
+//! and an `MpscConsumer`, which can't
+
+#[cfg(not(loom))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use crate::cache_padded::CachePadded;
+
+
+struct Slot {
+    /// the position this slot is ready for: `pos` when a producer may write position `pos`
+    /// into it, `pos + 1` once that value is written and the consumer may read it
+    sequence: AtomicUsize,
+    value: AtomicUsize,
+}
+
+/// producers claim a position by advancing `tail` with a compare-exchange, so no two of them
+/// ever get the same slot, and publish the value by moving the slot's `sequence` on with
+/// Release once it is stored. The consumer reads a slot only after seeing that with Acquire,
+/// then hands it to the producers one lap later by setting `sequence` to `pos + N`.
+/// Positions count up forever with wrapping arithmetic, `pos % N` is the slot index.
+pub struct MpscRingBuffer<const N: usize> {
+    slots: [Slot; N],
+    head: CachePadded<AtomicUsize>,
+    tail: CachePadded<AtomicUsize>,
+}
+
+impl<const N: usize> MpscRingBuffer<N> {
+    /// a capacity of 0 fails to compile, there would be no slot to index
+    const NON_ZERO: () = assert!(N > 0, "capacity of MpscRingBuffer must be non-zero");
+
+    pub fn new() -> Self {
+        let () = Self::NON_ZERO;
+        let slots = std::array::from_fn(|pos| Slot {
+            sequence: AtomicUsize::new(pos),
+            value: AtomicUsize::new(0),
+        });
+
+        Self {
+            slots,
+            head: CachePadded::new(AtomicUsize::new(0)),
+            tail: CachePadded::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// push a single element, handing it back when the buffer is full,
+    /// may be called from any number of threads at once
+    pub fn push(&self, value: usize) -> Result<(), usize> {
+        let mut pos = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos % N];
+            // Acquire: the consumer is done reading the slot's previous value
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            match sequence.wrapping_sub(pos) as isize {
+                0 => match self.tail.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        slot.value.store(value, Ordering::Relaxed);
+                        // Release: publish the value to the consumer
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(actual) => pos = actual,
+                },
+                // the slot still holds the value from one lap ago
+                diff if diff < 0 => return Err(value),
+                // another producer claimed `pos` meanwhile
+                _ => pos = self.tail.load(Ordering::Relaxed),
+            }
+        }
+    }
+    /// pop the oldest element, if any
+    pub fn pop(&mut self) -> Option<usize> {
+        self.pop_shared()
+    }
+    /// clear `data` and pop everything published so far into it,
+    /// returns the number of elements actually read
+    pub fn n_read(&mut self, data: &mut Vec<usize>) -> usize {
+        self.n_read_shared(data)
+    }
+
+    /// only ever called by one thread at a time, through `&mut self` or the one
+    /// `MpscConsumer`, two callers could both read the slot at `head`
+    fn pop_shared(&self) -> Option<usize> {
+        let pos = self.head.load(Ordering::Relaxed);
+        let slot = &self.slots[pos % N];
+        // Acquire: see the value the producer published
+        if slot.sequence.load(Ordering::Acquire) != pos.wrapping_add(1) {
+            return None;
+        }
+        let value = slot.value.load(Ordering::Relaxed);
+        // Release: the read is done before a producer may reuse the slot
+        slot.sequence.store(pos.wrapping_add(N), Ordering::Release);
+        self.head.store(pos.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+    /// same rule as `pop_shared`
+    fn n_read_shared(&self, data: &mut Vec<usize>) -> usize {
+        data.clear();
+        data.extend(std::iter::from_fn(|| self.pop_shared()).take(N));
+        data.len()
+    }
+
+    /// number of positions claimed by producers and not yet read, includes values that
+    /// are still being written, only a snapshot while the producers are active
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(head).min(N)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Default for MpscRingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// the producing half returned by `split`, clone it for every producer thread
+#[derive(Clone)]
+pub struct MpscProducer<const N: usize> {
+    ringbuffer: Arc<MpscRingBuffer<N>>,
+}
+
+/// the consuming half returned by `split`, it can't be cloned so there is only
+/// ever one consumer
+pub struct MpscConsumer<const N: usize> {
+    ringbuffer: Arc<MpscRingBuffer<N>>,
+}
+
+impl<const N: usize> MpscRingBuffer<N> {
+    /// split the buffer into a producer handle and the one consumer handle, each can be
+    /// sent to its own thread
+    pub fn split(self) -> (MpscProducer<N>, MpscConsumer<N>) {
+        let ringbuffer = Arc::new(self);
+        (
+            MpscProducer { ringbuffer: Arc::clone(&ringbuffer) },
+            MpscConsumer { ringbuffer },
+        )
+    }
+}
+
+impl<const N: usize> MpscProducer<N> {
+    /// see `MpscRingBuffer::push`
+    pub fn push(&self, value: usize) -> Result<(), usize> {
+        self.ringbuffer.push(value)
+    }
+}
+
+impl<const N: usize> MpscConsumer<N> {
+    pub fn pop(&mut self) -> Option<usize> {
+        self.ringbuffer.pop_shared()
+    }
+    pub fn read(&mut self, data: &mut Vec<usize>) -> usize {
+        self.ringbuffer.n_read_shared(data)
+    }
+    pub fn len(&self) -> usize {
+        self.ringbuffer.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ringbuffer.is_empty()
+    }
+}
+
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::collections::HashSet;
+    use std::thread;
+    use super::MpscRingBuffer;
+
+    #[test]
+    fn basics() {
+        let mut ringbuffer = MpscRingBuffer::<3>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.capacity(), 3);
+        assert_eq!(ringbuffer.pop(), None);
+
+        for i in 1..=3 {
+            assert_eq!(ringbuffer.push(i), Ok(()));
+        }
+        assert_eq!(ringbuffer.push(4), Err(4));
+        assert_eq!(ringbuffer.len(), 3);
+        assert_eq!(ringbuffer.pop(), Some(1));
+
+        // wraps around
+        assert_eq!(ringbuffer.push(4), Ok(()));
+        assert_eq!(ringbuffer.n_read(&mut result), 3);
+        assert_eq!(result, vec![2, 3, 4]);
+        assert!(ringbuffer.is_empty());
+    }
+
+    #[test]
+    fn multi_producer() {
+        let producers = 4;
+        let count = 25_000;
+        let (producer, mut consumer) = MpscRingBuffer::<64>::new().split();
+
+        let handles: Vec<_> = (0..producers)
+            .map(|p| {
+                let producer = producer.clone();
+                thread::spawn(move || {
+                    for i in 0..count {
+                        while producer.push(p * count + i).is_err() {
+                            thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // every value exactly once, and each producer's values in the order it pushed them
+        let mut seen = HashSet::new();
+        let mut last = vec![None; producers];
+        while seen.len() < producers * count {
+            match consumer.pop() {
+                Some(value) => {
+                    assert!(seen.insert(value), "{} received twice", value);
+                    let p = value / count;
+                    assert!(last[p] < Some(value));
+                    last[p] = Some(value);
+                }
+                None => thread::yield_now(),
+            }
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(consumer.pop(), None);
+        assert!(consumer.is_empty());
+    }
+}
+
+/// run with `RUSTFLAGS="--cfg loom" cargo test --lib ringbuffer_mpsc::loom_test`
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::thread;
+    use super::MpscRingBuffer;
+
+    #[test]
+    fn two_producers() {
+        loom::model(|| {
+            let (producer, mut consumer) = MpscRingBuffer::<2>::new().split();
+            let handles: Vec<_> = (1..=2)
+                .map(|value| {
+                    let producer = producer.clone();
+                    thread::spawn(move || producer.push(value).unwrap())
+                })
+                .collect();
+
+            let mut received = Vec::new();
+            while received.len() < 2 {
+                match consumer.pop() {
+                    Some(value) => received.push(value),
+                    None => thread::yield_now(),
+                }
+            }
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            received.sort();
+            assert_eq!(received, vec![1, 2]);
+        });
+    }
+}
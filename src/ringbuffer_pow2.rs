@@ -0,0 +1,189 @@
+//! generic ring buffer whose capacity is a power of two
+//! the type T must implement Copy trait
+//!
+//! `head` and `tail` are free running counters, the slot of a position is `pos & (N - 1)` and
+//! the number of buffered elements is `tail - head`, so there is no modulo and no shared
+//! counter to keep in sync
+
+use core::cmp::min;
+use core::mem::MaybeUninit;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+
+/// `N` must be a power of two, anything else fails to compile:
+///
+/// ```compile_fail
+/// let ringbuffer = ringbuffer::ringbuffer_pow2::RingBufferPow2::<u8, 10>::new();
+/// ```
+pub struct RingBufferPow2<T, const N: usize> {
+    buffer: [MaybeUninit<T>; N],
+    head: usize,
+    tail: usize,
+}
+
+impl<T, const N: usize> RingBufferPow2<T, N>
+where
+    T: Copy
+{
+    const MASK: usize = {
+        assert!(N.is_power_of_two(), "capacity of RingBufferPow2 must be a power of two");
+        N - 1
+    };
+
+    pub fn new() -> Self {
+        let _ = Self::MASK;
+        Self {
+            buffer: [MaybeUninit::uninit(); N],
+            head: 0,
+            tail: 0,
+        }
+    }
+
+    /// write as many elements of `data` as there is free space for,
+    /// returns the number of elements actually written
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::ptr_arg)]
+    pub fn n_write(&mut self, data: &Vec<T>) -> usize {
+        self.write_from(data)
+    }
+    /// same as `n_write` but takes any slice
+    pub fn write_from(&mut self, data: &[T]) -> usize {
+        let write_count = min(data.len(), N - self.len());
+        let index = self.tail & Self::MASK;
+        let (front, back) = data[..write_count].split_at(min(write_count, N - index));
+        for (slot, elem) in self.buffer[index..].iter_mut().zip(front) {
+            slot.write(*elem);
+        }
+        for (slot, elem) in self.buffer.iter_mut().zip(back) {
+            slot.write(*elem);
+        }
+        self.tail = self.tail.wrapping_add(write_count);
+        write_count
+    }
+    /// clear `data` and move every buffered element into it,
+    /// returns the number of elements actually read
+    #[cfg(feature = "alloc")]
+    pub fn n_read(&mut self, data: &mut Vec<T>) -> usize {
+        data.clear();
+        let (front, back) = self.as_slices();
+        data.extend_from_slice(front);
+        data.extend_from_slice(back);
+        self.head = self.tail;
+        data.len()
+    }
+
+    /// number of elements currently buffered
+    pub fn len(&self) -> usize {
+        self.tail.wrapping_sub(self.head)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// the buffered elements as two contiguous slices in FIFO order, the second one is
+    /// empty when the data doesn't wrap
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let index = self.head & Self::MASK;
+        let len = self.len();
+        let (front, back) = if len <= (N - index) {
+            (&self.buffer[index..index + len], &self.buffer[..0])
+        } else {
+            (&self.buffer[index..], &self.buffer[..len - (N - index)])
+        };
+        // SAFETY: both slices lie within the live region, whose slots are all initialized,
+        // and MaybeUninit<T> has the same layout as T
+        unsafe {
+            (
+                &*(front as *const [MaybeUninit<T>] as *const [T]),
+                &*(back as *const [MaybeUninit<T>] as *const [T]),
+            )
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> Default for RingBufferPow2<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Copy, const N: usize> crate::RingBuffer<T> for RingBufferPow2<T, N> {
+    fn write(&mut self, data: &[T]) -> usize {
+        self.write_from(data)
+    }
+    fn read(&mut self, data: &mut Vec<T>) -> usize {
+        self.n_read(data)
+    }
+    fn len(&self) -> usize {
+        RingBufferPow2::len(self)
+    }
+    fn capacity(&self) -> usize {
+        N
+    }
+}
+
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::RingBufferPow2;
+    use crate::ringbuffer::RingBuffer;
+
+    #[test]
+    fn basics() {
+        let mut ringbuffer = RingBufferPow2::<i32, 8>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.capacity(), 8);
+
+        assert_eq!(ringbuffer.n_write(&vec![1; 6]), 6);
+        assert_eq!(ringbuffer.n_read(&mut result), 6);
+        assert!(ringbuffer.is_empty());
+
+        assert_eq!(ringbuffer.write_from(&[1, 2, 3, 4, 5]), 5);
+        assert_eq!(ringbuffer.write_from(&[6, 7, 8, 9]), 3);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.n_read(&mut result), 8);
+        assert_eq!(result, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(ringbuffer.n_read(&mut result), 0);
+    }
+
+    #[test]
+    fn same_as_generic() {
+        let mut pow2 = RingBufferPow2::<u32, 16>::new();
+        let mut generic = RingBuffer::<u32, 16>::new();
+        let mut pow2_result = Vec::new();
+        let mut generic_result = Vec::new();
+
+        let mut next = 0;
+        for round in 0..1000 {
+            let data: Vec<u32> = (next..next + (round * 7 % 13) as u32).collect();
+            next += data.len() as u32;
+            assert_eq!(pow2.n_write(&data), generic.n_write(&data));
+            assert_eq!(pow2.len(), generic.len());
+            if round % 3 != 0 {
+                assert_eq!(pow2.n_read(&mut pow2_result), generic.n_read(&mut generic_result));
+                assert_eq!(pow2_result, generic_result);
+            }
+        }
+    }
+
+    #[test]
+    fn counters_wrap() {
+        let mut ringbuffer = RingBufferPow2::<i32, 4>::new();
+        let mut result = Vec::new();
+        ringbuffer.head = usize::MAX - 1;
+        ringbuffer.tail = usize::MAX - 1;
+
+        assert_eq!(ringbuffer.write_from(&[1, 2, 3, 4]), 4);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![1, 2, 3, 4]);
+    }
+}
@@ -1,110 +1,716 @@
-/// thread safe ring buffer
-/// the type T is here for example limited to usize
-
+//! thread safe ring buffer
+//! the elements are plain integers stored in atomics, `usize` by default
 
+#[cfg(not(loom))]
 use std::sync::atomic::{
+    fence,
+    AtomicBool,
+    AtomicUsize, 
+    AtomicU32,
+    AtomicU64,
+    Ordering,
+};
+#[cfg(loom)]
+use loom::sync::atomic::{
+    fence,
+    AtomicBool,
     AtomicUsize, 
+    AtomicU32,
+    AtomicU64,
     Ordering,
 };
 use std::cmp::min;
+use crate::cache_padded::CachePadded;
+use crate::error::RingBufferError;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use std::future::poll_fn;
+use std::task::{Poll, Waker};
+
+
+/// atomic integer types the ring buffer can store its elements in
+pub trait AtomicInt {
+    type Value: Copy;
 
+    /// an atomic holding 0, a constant so `RingBuffer::new` can be a `const fn`
+    #[cfg(not(loom))]
+    const ZERO: Self;
+    /// loom's atomics can't be created in a const context
+    #[cfg(loom)]
+    fn zero() -> Self;
+    fn load(&self, order: Ordering) -> Self::Value;
+    fn store(&self, value: Self::Value, order: Ordering);
+    /// the values of `slots` in place, without loading them one by one
+    ///
+    /// # Safety
+    /// nothing may store into `slots` while the returned slice is alive
+    #[cfg(not(loom))]
+    unsafe fn as_values(slots: &[Self]) -> &[Self::Value]
+    where
+        Self: Sized;
+}
+
+macro_rules! impl_atomic_int {
+    ($atomic:ty, $value:ty) => {
+        impl AtomicInt for $atomic {
+            type Value = $value;
+
+            #[cfg(not(loom))]
+            const ZERO: Self = <$atomic>::new(0);
+            #[cfg(loom)]
+            fn zero() -> Self {
+                <$atomic>::new(0)
+            }
+            fn load(&self, order: Ordering) -> $value {
+                <$atomic>::load(self, order)
+            }
+            fn store(&self, value: $value, order: Ordering) {
+                <$atomic>::store(self, value, order)
+            }
+            #[cfg(not(loom))]
+            unsafe fn as_values(slots: &[Self]) -> &[$value] {
+                // SAFETY: an atomic integer has the same in-memory representation as the
+                // integer, the caller rules out stores while the slice is alive
+                unsafe { std::slice::from_raw_parts(slots.as_ptr() as *const $value, slots.len()) }
+            }
+        }
+    };
+}
+
+impl_atomic_int!(AtomicUsize, usize);
+impl_atomic_int!(AtomicU32, u32);
+impl_atomic_int!(AtomicU64, u64);
+
+
+/// single producer / single consumer: `tail` is only advanced by the producer and published
+/// with Release after the slots are written, `head` is only advanced by the consumer and
+/// published with Release after the slots are read, each side loads the other's counter with
+/// Acquire. Both count positions up forever with wrapping arithmetic, `pos % N` is the slot
+/// index, so a full buffer (tail - head == N) can be told apart from an empty one
+/// (tail == head) without a shared counter.
+/// `write_overwrite` lets the producer move `head` as well, to take the oldest slots back
+/// before writing into them, so the consumer releases slots with a compare-exchange and
+/// discards what it read if the producer got there first. As `head` never comes back to a
+/// value it had, that compare-exchange can't be fooled by the producer lapping the buffer.
+/// `high_watermark` is telemetry only, it is updated Relaxed and takes no part in the above.
+pub struct RingBuffer<const N: usize, A: AtomicInt = AtomicUsize> {
+    buffer: [A; N],
+    head: CachePadded<AtomicUsize>,
+    tail: CachePadded<AtomicUsize>,
+    high_watermark: CachePadded<AtomicUsize>,
+    reader_waker: WakerSlot,
+    /// set by the producer once it is done writing
+    closed: AtomicBool,
+    /// set by `write_overwrite` whenever it loses data, cleared by `take_overrun`
+    overrun: AtomicBool,
+}
+
+/// the waker of a pending `read_async`, `registered` lets writers skip the lock while
+/// nobody is waiting.
+/// The reader sets `registered` and then looks at `tail` again, a writer moves `tail` and
+/// then looks at `registered`, with a SeqCst fence between the two steps on both sides at
+/// least one of them sees the other's store, so a write can't slip in unnoticed.
+struct WakerSlot {
+    waker: Mutex<Option<Waker>>,
+    registered: AtomicBool,
+}
 
-pub struct RingBuffer<const N: usize> {
-    buffer: [AtomicUsize; N],
-    head: AtomicUsize,
-    tail: AtomicUsize,
-    used_count: AtomicUsize,
+impl WakerSlot {
+    #[cfg(not(loom))]
+    const fn new() -> Self {
+        Self { waker: Mutex::new(None), registered: AtomicBool::new(false) }
+    }
+    #[cfg(loom)]
+    fn new() -> Self {
+        Self { waker: Mutex::new(None), registered: AtomicBool::new(false) }
+    }
+    fn register(&self, waker: &Waker) {
+        let mut slot = self.waker.lock().unwrap();
+        if !slot.as_ref().is_some_and(|current| current.will_wake(waker)) {
+            *slot = Some(waker.clone());
+        }
+        self.registered.store(true, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+    }
+    fn wake(&self) {
+        fence(Ordering::SeqCst);
+        if self.registered.load(Ordering::Relaxed) && self.registered.swap(false, Ordering::Relaxed) {
+            if let Some(waker) = self.waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+    }
 }
 
+/// occupancy of a thread-safe ring buffer at one point in time, returned by `snapshot`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferStats {
+    pub len: usize,
+    pub capacity: usize,
+    pub free: usize,
+}
 
-impl<const N:usize> RingBuffer<N> 
+impl<const N:usize, A: AtomicInt> RingBuffer<N, A> 
 {  
+    /// a capacity of 0 fails to compile, there would be no slot to index
+    const NON_ZERO: () = assert!(N > 0, "capacity of RingBuffer must be non-zero");
+
+    /// a `const fn`, so the buffer can be put in a `static` and shared without an `Arc`
+    #[cfg(not(loom))]
+    pub const fn new() -> Self {
+        let () = Self::NON_ZERO;
+        Self {
+            buffer: [const { A::ZERO }; N],
+            head: CachePadded::new(AtomicUsize::new(0)),
+            tail: CachePadded::new(AtomicUsize::new(0)),
+            high_watermark: CachePadded::new(AtomicUsize::new(0)),
+            reader_waker: WakerSlot::new(),
+            closed: AtomicBool::new(false),
+            overrun: AtomicBool::new(false),
+        }
+    }
+    #[cfg(loom)]
     pub fn new() -> Self {
-        let mut b = [(); N].map(|_| AtomicUsize::new(0));
+        let () = Self::NON_ZERO;
+        let b = [(); N].map(|_| A::zero());
 
         Self {
             buffer: b,
-            head: AtomicUsize::new(0),
-            tail: AtomicUsize::new(0),
-            used_count: AtomicUsize::new(0),
+            head: CachePadded::new(AtomicUsize::new(0)),
+            tail: CachePadded::new(AtomicUsize::new(0)),
+            high_watermark: CachePadded::new(AtomicUsize::new(0)),
+            reader_waker: WakerSlot::new(),
+            closed: AtomicBool::new(false),
+            overrun: AtomicBool::new(false),
         }
     }
 
-    fn n_write(&self, data: &Vec<AtomicUsize>) -> usize {
-        if N - self.used_count.load(Ordering::Relaxed) == 0 {
+    /// number of elements between `head` and `tail`, at most `N`: a consumer's `head` may
+    /// be stale when the producer overwrote meanwhile, its compare-exchange then fails anyway
+    fn distance(head: usize, tail: usize) -> usize {
+        tail.wrapping_sub(head).min(N)
+    }
+
+    #[allow(clippy::ptr_arg)]
+    pub fn n_write(&self, data: &Vec<A>) -> usize {
+        self.write_values(data.len(), data.iter().map(|elem| elem.load(Ordering::Relaxed)))
+    }
+    /// same as `n_write` but takes plain values instead of atomics
+    pub fn write_from(&self, data: &[A::Value]) -> usize {
+        self.write_values(data.len(), data.iter().copied())
+    }
+    /// write at most `len` elements of `values`, as many as there is free space for
+    fn write_values(&self, len: usize, values: impl Iterator<Item = A::Value>) -> usize {
+        // Acquire: slots the consumer has released are done being read
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Relaxed);
+        let free = N - Self::distance(head, tail);
+        if free == 0 {
             // println!("buffer full");
             return 0;
         }
-        let write_count = min(data.len(),N - self.used_count.load(Ordering::Relaxed));
+        let write_count = min(len, free);
+        let index = tail % N;
+        let slots = self.buffer[index..].iter().chain(&self.buffer[..index]);
+        for (slot, value) in slots.zip(values).take(write_count) {
+            slot.store(value, Ordering::Relaxed);
+        }
+
+        // Release: publish the written slots to the consumer
+        self.tail.store(tail.wrapping_add(write_count), Ordering::Release);
+        self.reader_waker.wake();
+        self.record_len(N - free + write_count);
+        write_count
+    }
+    /// write every element of `data`, the newest ones win: unread elements that are in the
+    /// way are overwritten, returns the number of elements lost (overwritten ones plus any
+    /// of `data` that didn't make it because `data` is longer than `N`)
+    pub fn write_overwrite(&self, data: &[A::Value]) -> usize {
+        let skipped = data.len().saturating_sub(N);
+        let data = &data[skipped..];
         let tail = self.tail.load(Ordering::Relaxed);
-        let new_tail;
-        if write_count <= (N - tail) {
-            for i in 0..write_count {
-                self.buffer[tail + i].store(data[i].load(Ordering::Relaxed), Ordering::Relaxed);
+        let mut head = self.head.load(Ordering::Acquire);
+        let overwritten = loop {
+            let overwritten = (Self::distance(head, tail) + data.len()).saturating_sub(N);
+            if overwritten == 0 {
+                break 0;
             }
+            // take the oldest slots away from the consumer before writing into them, Acquire
+            // so the consumer's reads of them are done, Release pairs with its next head load
+            match self.head.compare_exchange_weak(
+                head,
+                head.wrapping_add(overwritten),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break overwritten,
+                Err(actual) => head = actual,
+            }
+        };
 
-            new_tail = tail.wrapping_add(write_count);
+        let index = tail % N;
+        let slots = self.buffer[index..].iter().chain(&self.buffer[..index]);
+        for (slot, value) in slots.zip(data) {
+            slot.store(*value, Ordering::Relaxed);
+        }
+        if overwritten + skipped > 0 {
+            // Relaxed is enough, the Release store of `tail` below publishes it together
+            // with the data
+            self.overrun.store(true, Ordering::Relaxed);
+        }
+        self.tail.store(tail.wrapping_add(data.len()), Ordering::Release);
+        self.reader_waker.wake();
+        self.record_len(Self::distance(head, tail) + data.len() - overwritten);
+        overwritten + skipped
+    }
+    /// push a single element, handing it back when the buffer is full
+    pub fn try_push(&self, value: A::Value) -> Result<(), A::Value> {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Relaxed);
+        if Self::distance(head, tail) == N {
+            return Err(value);
+        }
+        self.buffer[tail % N].store(value, Ordering::Relaxed);
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        self.reader_waker.wake();
+        self.record_len(Self::distance(head, tail) + 1);
+        Ok(())
+    }
+    /// write all of `data` or nothing: `CapacityExceeded` if it could never fit, `Full` if
+    /// it doesn't fit right now. The consumer can only make more room meanwhile, so the
+    /// check holds up to the write.
+    pub fn write_exact(&self, data: &[A::Value]) -> Result<(), RingBufferError> {
+        if data.len() > N {
+            return Err(RingBufferError::CapacityExceeded { requested: data.len(), capacity: N });
+        }
+        if data.len() > self.free() {
+            return Err(RingBufferError::Full);
+        }
+        self.write_from(data);
+        Ok(())
+    }
+    /// same as `try_pop` but an empty buffer is `Err(Empty)`
+    pub fn read_one(&self) -> Result<A::Value, RingBufferError> {
+        self.try_pop().ok_or(RingBufferError::Empty)
+    }
+    /// pop the oldest element, if any
+    pub fn try_pop(&self) -> Option<A::Value> {
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            let tail = self.tail.load(Ordering::Acquire);
+            if head == tail {
+                return None;
+            }
+            let value = self.buffer[head % N].load(Ordering::Relaxed);
+            match self.release_head(head, 1) {
+                Ok(()) => return Some(value),
+                Err(actual) => head = actual,
+            }
+        }
+    }
+    pub fn n_read(&self,data: &mut Vec<A::Value>) -> usize {
+        self.n_read_upto(data, N)
+    }
+    /// copy at most `out.len()` buffered elements into `out` without allocating, the rest
+    /// of `out` is left as it was, returns the number of elements actually read
+    pub fn read_into_slice(&self, out: &mut [A::Value]) -> usize {
+        // same protocol as `n_read_upto`
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            let tail = self.tail.load(Ordering::Acquire);
+            let read_count = min(out.len(), Self::distance(head, tail));
+            if read_count == 0 {
+                return 0;
+            }
+
+            let index = head % N;
+            let slots = self.buffer[index..].iter().chain(&self.buffer[..index]);
+            for (value, slot) in out[..read_count].iter_mut().zip(slots) {
+                *value = slot.load(Ordering::Relaxed);
+            }
+
+            match self.release_head(head, read_count) {
+                Ok(()) => return read_count,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+    /// the buffered elements as two contiguous slices in FIFO order, see `Consumer::read_chunk`
+    ///
+    /// # Safety
+    /// the slices point straight into the slots, so nothing may store into them while they
+    /// are alive: drop them before calling `release`, and don't call `write_overwrite`
+    /// meanwhile, it takes the oldest slots back. The other writes only store into free
+    /// slots and are fine.
+    #[cfg(not(loom))]
+    unsafe fn read_chunk(&self) -> (&[A::Value], &[A::Value]) {
+        let head = self.head.load(Ordering::Acquire);
+        // Acquire: see the slots the producer has published
+        let tail = self.tail.load(Ordering::Acquire);
+        let len = Self::distance(head, tail);
+        let index = head % N;
+        let (front, back) = if len <= N - index {
+            (&self.buffer[index..index + len], &self.buffer[..0])
         } else {
-            new_tail = write_count - (N - tail);
+            (&self.buffer[index..], &self.buffer[..len - (N - index)])
+        };
+        // SAFETY: the slots are published and the caller keeps stores away from them
+        unsafe { (A::as_values(front), A::as_values(back)) }
+    }
+    /// hand the oldest `n` slots back to the producer after a `read_chunk`, panics if fewer
+    /// than `n` elements are buffered
+    fn release(&self, mut n: usize) {
+        let mut head = self.head.load(Ordering::Acquire);
+        let len = Self::distance(head, self.tail.load(Ordering::Acquire));
+        assert!(n <= len, "cannot release {} elements, only {} are buffered", n, len);
+        while let Err(actual) = self.release_head(head, n) {
+            // `write_overwrite` took the oldest slots itself, they are released already
+            let taken = Self::distance(head, actual);
+            if taken >= n {
+                return;
+            }
+            n -= taken;
+            head = actual;
+        }
+    }
+    /// like `n_read` but reads at most `max` elements, the rest stays buffered
+    pub fn n_read_upto(&self, data: &mut Vec<A::Value>, max: usize) -> usize {
+        // Acquire on head: a tail loaded afterwards is at least as new as the one the
+        // producer saw when it last moved head
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            data.clear();
+            // Acquire: see the slots the producer has published, tail is loaded once so only
+            // what was seen here is read and released again
+            let tail = self.tail.load(Ordering::Acquire);
+            let read_count = min(max, Self::distance(head, tail));
+            if read_count == 0 {
+                // println!("buffer empty");
+                return 0;
+            }
 
-            for i in 0..(N - tail) {
-                self.buffer[tail + i].store(data[i].load(Ordering::Relaxed), Ordering::Relaxed);               
+            let index = head % N;
+            if read_count <= (N - index) {
+                for slot in &self.buffer[index..(index + read_count)] {
+                    data.push(slot.load(Ordering::Relaxed));
+                }
+            } else {
+                for slot in &self.buffer[index..] {
+                    data.push(slot.load(Ordering::Relaxed));
+                }
+                for slot in &self.buffer[..read_count - (N - index)] {
+                    data.push(slot.load(Ordering::Relaxed));
+                }
             }
-            for i in 0..new_tail {
-                self.buffer[i].store(data[(N - tail) + i].load(Ordering::Relaxed), Ordering::Relaxed);
+
+            match self.release_head(head, read_count) {
+                Ok(()) => return read_count,
+                Err(actual) => head = actual,
             }
         }
+    }
+    /// like `n_read` but waits for data instead of returning 0 when the buffer is empty,
+    /// the task is woken by the next write. Only one read may be pending at a time, as
+    /// there is only one consumer. Returns 0 once the buffer is closed and empty.
+    pub async fn read_async(&self, data: &mut Vec<A::Value>) -> usize {
+        poll_fn(|cx| {
+            if self.n_read(data) > 0 {
+                return Poll::Ready(data.len());
+            }
+            self.reader_waker.register(cx.waker());
+            // a write or close that finished before the registration didn't wake us
+            match self.n_read(data) {
+                0 if self.is_closed() => Poll::Ready(0),
+                0 => Poll::Pending,
+                read_count => Poll::Ready(read_count),
+            }
+        })
+        .await
+    }
+    /// hand `count` read slots starting at `head` back to the producer, fails with the
+    /// current head if `write_overwrite` took some of them meanwhile, the values read
+    /// may then already be overwritten
+    fn release_head(&self, head: usize, count: usize) -> Result<(), usize> {
+        // Release: the slot reads are done before the producer may reuse the slots
+        self.head
+            .compare_exchange(head, head.wrapping_add(count), Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| ())
+    }
 
-        self.used_count.fetch_add(write_count, Ordering::Release);
-        self.tail.store(new_tail, Ordering::Release);
-        write_count
+    /// number of elements currently buffered, only a snapshot while the other side is active
+    pub fn len(&self) -> usize {
+        Self::distance(self.head.load(Ordering::Acquire), self.tail.load(Ordering::Acquire))
     }
-    fn n_read(&self,data: &mut Vec<usize>) -> usize {
-        data.clear();
-        let read_count = self.used_count.load(Ordering::Relaxed);
-        if read_count == 0 {
-            // println!("buffer empty");
-            return 0;
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+    /// number of elements that can be written before the buffer is full, seen from the
+    /// producer this can only grow until its next write
+    pub fn free(&self) -> usize {
+        N - self.len()
+    }
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    /// `len` and `free` computed from a single Acquire load of each counter, so they always
+    /// add up to `capacity`. `head` and `tail` can't be loaded at the same instant though,
+    /// while the other side is active the snapshot is a state the buffer passed through
+    /// recently, not necessarily the current one.
+    pub fn snapshot(&self) -> BufferStats {
+        let len = self.len();
+        BufferStats { len, capacity: N, free: N - len }
+    }
+    /// tell the consumer no more data is coming, what is already buffered can still be read.
+    /// Wakes a pending `read_async`, which then returns 0 once the buffer is empty.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.reader_waker.wake();
+    }
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+    /// whether `write_overwrite` lost unread data since the last call, clears the flag
+    pub fn take_overrun(&self) -> bool {
+        self.overrun.swap(false, Ordering::Relaxed)
+    }
+    /// check the bookkeeping, for tests and debugging: `tail` is at most `N` positions past
+    /// `head`, returns which rule is broken if any. Only meaningful while neither side is in
+    /// the middle of an operation.
+    pub fn check_invariants(&self) -> Result<(), &'static str> {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) > N {
+            return Err("tail is more than N positions past head");
         }
+        Ok(())
+    }
+
+    /// the highest number of elements seen buffered right after a write
+    pub fn high_watermark(&self) -> usize {
+        self.high_watermark.load(Ordering::Relaxed)
+    }
+    /// start tracking again from what is buffered right now
+    pub fn reset_high_watermark(&self) {
+        self.high_watermark.store(self.len(), Ordering::Relaxed);
+    }
+    /// the producer's view of the length after a write, the consumer may already have
+    /// read some of it, so the watermark can only err on the high side
+    fn record_len(&self, len: usize) {
+        // the plain load keeps the common case, no new maximum, free of read-modify-writes
+        if len > self.high_watermark.load(Ordering::Relaxed) {
+            self.high_watermark.fetch_max(len, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<const N: usize, A: AtomicInt> crate::RingBuffer<A::Value> for RingBuffer<N, A> {
+    fn write(&mut self, data: &[A::Value]) -> usize {
+        self.write_from(data)
+    }
+    fn read(&mut self, data: &mut Vec<A::Value>) -> usize {
+        self.n_read(data)
+    }
+    fn len(&self) -> usize {
+        RingBuffer::len(self)
+    }
+    fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize, A: AtomicInt> Default for RingBuffer<N, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// the producing half returned by `split`, it can't be cloned so there is only
+/// ever one producer
+pub struct Producer<const N: usize, A: AtomicInt = AtomicUsize> {
+    ringbuffer: Arc<RingBuffer<N, A>>,
+}
+
+/// the consuming half returned by `split`, it can't be cloned so there is only
+/// ever one consumer
+pub struct Consumer<const N: usize, A: AtomicInt = AtomicUsize> {
+    ringbuffer: Arc<RingBuffer<N, A>>,
+}
 
-        let head = self.head.load(Ordering::Relaxed);
-        let new_head;
-        if read_count <= (N - head) {
-            for i in head..(head + read_count) {
-                data.push(self.buffer[i].load(Ordering::Relaxed));
+impl<const N: usize, A: AtomicInt> RingBuffer<N, A> {
+    /// a new buffer behind an `Arc`, clone it for the other thread
+    pub fn new_shared() -> Arc<Self> {
+        Arc::new(Self::new())
+    }
+    /// split the buffer into a producer and a consumer handle, enforcing the single
+    /// producer / single consumer contract at the type level
+    pub fn split(self) -> (Producer<N, A>, Consumer<N, A>) {
+        let ringbuffer = Arc::new(self);
+        (
+            Producer { ringbuffer: Arc::clone(&ringbuffer) },
+            Consumer { ringbuffer },
+        )
+    }
+}
+
+/// same as `RingBuffer::split`
+pub fn split<const N: usize, A: AtomicInt>(buf: RingBuffer<N, A>) -> (Producer<N, A>, Consumer<N, A>) {
+    buf.split()
+}
+
+impl<const N: usize, A: AtomicInt> Producer<N, A> {
+    pub fn push(&mut self, value: A::Value) -> Result<(), A::Value> {
+        self.ringbuffer.try_push(value)
+    }
+    #[allow(clippy::ptr_arg)]
+    pub fn write(&mut self, data: &Vec<A>) -> usize {
+        self.ringbuffer.n_write(data)
+    }
+}
+
+impl<const N: usize, A: AtomicInt> Consumer<N, A> {
+    pub fn pop(&mut self) -> Option<A::Value> {
+        self.ringbuffer.try_pop()
+    }
+    pub fn read(&mut self, data: &mut Vec<A::Value>) -> usize {
+        self.ringbuffer.n_read(data)
+    }
+    /// the buffered elements as two contiguous slices in FIFO order, the second one is empty
+    /// when the data doesn't wrap, to be looked at in place and then handed back with
+    /// `release`. The slices point straight into the slots, so this lives here and borrows
+    /// the consumer rather than being a `&self` method on `RingBuffer`: the only store into
+    /// a buffered slot is `write_overwrite`, which a `Producer` can't call, and the borrow
+    /// keeps `release` away until the slices are dropped.
+    #[cfg(not(loom))]
+    pub fn read_chunk(&mut self) -> (&[A::Value], &[A::Value]) {
+        // SAFETY: after `split` the buffer is only reachable through the two handles, the
+        // producer only stores into free slots and `&mut self` rules out `release` meanwhile
+        unsafe { self.ringbuffer.read_chunk() }
+    }
+    /// hand the oldest `n` elements back to the producer after a `read_chunk`, panics if
+    /// fewer than `n` are buffered
+    pub fn release(&mut self, n: usize) {
+        self.ringbuffer.release(n)
+    }
+}
+
+
+/// wraps the buffer so readers sleep while it is empty and writers sleep while it is full,
+/// instead of spinning on `n_read`/`n_write`
+pub struct BlockingRingBuffer<const N: usize, A: AtomicInt = AtomicUsize> {
+    ringbuffer: RingBuffer<N, A>,
+    lock: Mutex<()>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl<const N: usize, A: AtomicInt> BlockingRingBuffer<N, A> {
+    pub fn new() -> Self {
+        Self {
+            ringbuffer: RingBuffer::new(),
+            lock: Mutex::new(()),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        }
+    }
+    /// a new buffer behind an `Arc`, clone it for the other thread
+    pub fn new_shared() -> Arc<Self> {
+        Arc::new(Self::new())
+    }
+    /// tell the consumer no more data is coming, a waiting `read_blocking` wakes up and
+    /// returns 0 once everything buffered has been read
+    pub fn close(&self) {
+        // under the lock, so a reader can't check the flag and then miss the notify
+        let guard = self.lock.lock().unwrap();
+        self.ringbuffer.close();
+        drop(guard);
+        self.not_empty.notify_all();
+    }
+    pub fn is_closed(&self) -> bool {
+        self.ringbuffer.is_closed()
+    }
+
+    /// write as much of `data` as fits, waiting until there is room for at least one element
+    #[allow(clippy::ptr_arg)]
+    pub fn write_blocking(&self, data: &Vec<A>) -> usize {
+        if data.is_empty() {
+            return 0;
+        }
+        let mut guard = self.lock.lock().unwrap();
+        loop {
+            let n = self.ringbuffer.n_write(data);
+            if n > 0 {
+                drop(guard);
+                self.not_empty.notify_one();
+                return n;
             }
-            new_head = head.wrapping_add(read_count);
-        } else {
-            new_head = read_count - (N - head);
-            for i in head..N {
-                data.push(self.buffer[i].load(Ordering::Relaxed));
+            guard = self.not_full.wait(guard).unwrap();
+        }
+    }
+    /// read every buffered element into `out`, waiting until there is at least one,
+    /// returns 0 once the buffer is closed and empty
+    pub fn read_blocking(&self, out: &mut Vec<A::Value>) -> usize {
+        let mut guard = self.lock.lock().unwrap();
+        loop {
+            let n = self.ringbuffer.n_read(out);
+            if n > 0 {
+                drop(guard);
+                self.not_full.notify_one();
+                return n;
+            }
+            if self.ringbuffer.is_closed() {
+                return 0;
+            }
+            guard = self.not_empty.wait(guard).unwrap();
+        }
+    }
+    /// like `read_blocking` but gives up after `dur`, returning 0 if nothing arrived or
+    /// the buffer is closed and empty
+    pub fn read_timeout(&self, out: &mut Vec<A::Value>, dur: Duration) -> usize {
+        let deadline = Instant::now() + dur;
+        let mut guard = self.lock.lock().unwrap();
+        loop {
+            let n = self.ringbuffer.n_read(out);
+            if n > 0 {
+                drop(guard);
+                self.not_full.notify_one();
+                return n;
             }
-            for i in 0..new_head {
-                data.push(self.buffer[i].load(Ordering::Relaxed));
+            let now = Instant::now();
+            if now >= deadline || self.ringbuffer.is_closed() {
+                return 0;
             }
+            guard = self.not_empty.wait_timeout(guard, deadline - now).unwrap().0;
         }
+    }
+}
 
-        self.used_count.fetch_sub(read_count, Ordering::Release);
-        self.head.store(new_head, Ordering::Release);
-        read_count
+impl<const N: usize, A: AtomicInt> Default for BlockingRingBuffer<N, A> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod test {
+    use std::cmp::min;
     use std::thread;
-    use std::sync::Arc;
-    use super::RingBuffer;
-    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Barrier};
+    use super::{BlockingRingBuffer, BufferStats, RingBuffer};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, AtomicU32, AtomicU64, Ordering};
+    use std::time::{Duration, Instant};
 
     #[test]
     fn basics() {
-        let mut ringbuffer = RingBuffer::<10>::new();
+        let ringbuffer = RingBuffer::<10>::new();
 
         // simple read/write
         let mut data: Vec<AtomicUsize> = Vec::new();
-        for i in 0..8 {
+        for _ in 0..8 {
             data.push(AtomicUsize::new(1));
         }
         let mut result = vec![1; 8];
@@ -117,6 +723,179 @@ mod test {
         assert_eq!(ringbuffer.n_write(&data), 2);
         assert_eq!(ringbuffer.n_read(&mut result), 10);
         assert_eq!(ringbuffer.n_read(&mut result), 0);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn other_widths() {
+        let ringbuffer = RingBuffer::<4, AtomicU32>::new();
+        let data: Vec<AtomicU32> = (1..=3).map(AtomicU32::new).collect();
+        let mut result: Vec<u32> = Vec::new();
+        assert_eq!(ringbuffer.n_write(&data), 3);
+        assert_eq!(ringbuffer.n_write(&data), 1);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![1, 2, 3, 1]);
+
+        let ringbuffer = RingBuffer::<4, AtomicU64>::new();
+        let data: Vec<AtomicU64> = [u64::MAX, 1 << 40].into_iter().map(AtomicU64::new).collect();
+        let mut result: Vec<u64> = Vec::new();
+        assert_eq!(ringbuffer.n_write(&data), 2);
+        assert_eq!(ringbuffer.n_read(&mut result), 2);
+        // wraps around the end of the buffer
+        assert_eq!(ringbuffer.n_write(&data), 2);
+        assert_eq!(ringbuffer.n_write(&data), 2);
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![u64::MAX, 1 << 40, u64::MAX, 1 << 40]);
+    }
+
+    #[test]
+    fn read_in_chunks() {
+        let ringbuffer = RingBuffer::<10>::new();
+        let data: Vec<AtomicUsize> = (0..8).map(AtomicUsize::new).collect();
+        let mut result = Vec::new();
+        ringbuffer.n_write(&data);
+        ringbuffer.n_read(&mut result);
+
+        // 8 elements wrapping around the end, read 3 at a time
+        assert_eq!(ringbuffer.n_write(&data), 8);
+        assert_eq!(ringbuffer.n_read_upto(&mut result, 3), 3);
+        assert_eq!(result, vec![0, 1, 2]);
+        assert_eq!(ringbuffer.n_read_upto(&mut result, 3), 3);
+        assert_eq!(result, vec![3, 4, 5]);
+        assert_eq!(ringbuffer.n_read_upto(&mut result, 3), 2);
+        assert_eq!(result, vec![6, 7]);
+        assert_eq!(ringbuffer.n_read_upto(&mut result, 3), 0);
+        assert_eq!(ringbuffer.n_read_upto(&mut result, 0), 0);
+    }
+
+    #[test]
+    fn push_pop() {
+        let ringbuffer = RingBuffer::<2>::new();
+        assert_eq!(ringbuffer.try_pop(), None);
+        assert_eq!(ringbuffer.try_push(1), Ok(()));
+        assert_eq!(ringbuffer.try_push(2), Ok(()));
+        assert_eq!(ringbuffer.try_push(3), Err(3));
+        assert_eq!(ringbuffer.try_pop(), Some(1));
+        assert_eq!(ringbuffer.try_push(3), Ok(()));
+        assert_eq!(ringbuffer.try_pop(), Some(2));
+        assert_eq!(ringbuffer.try_pop(), Some(3));
+        assert_eq!(ringbuffer.try_pop(), None);
+    }
+
+    #[test]
+    fn multi_thread_push_pop() {
+        let count = 100_000;
+        let consumer = Arc::new(RingBuffer::<16>::new());
+        let producer = Arc::clone(&consumer);
+
+        let t = thread::spawn(move || {
+            for i in 0..count {
+                while producer.try_push(i).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < count {
+            match consumer.try_pop() {
+                Some(value) => {
+                    assert_eq!(value, expected);
+                    expected += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        t.join().unwrap();
+        assert_eq!(consumer.try_pop(), None);
+    }
+
+    #[test]
+    fn split() {
+        let count = 100_000;
+        let (mut producer, mut consumer) = super::split(RingBuffer::<16>::new());
+
+        let t1 = thread::spawn(move || {
+            for i in 0..count {
+                while producer.push(i).is_err() {
+                    thread::yield_now();
+                }
+            }
+            let data = vec![AtomicUsize::new(count)];
+            while producer.write(&data) == 0 {
+                thread::yield_now();
+            }
+        });
+        let t2 = thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut output = Vec::new();
+            while received.len() < count {
+                match consumer.pop() {
+                    Some(value) => received.push(value),
+                    None => thread::yield_now(),
+                }
+            }
+            while received.len() < count + 1 {
+                consumer.read(&mut output);
+                received.extend_from_slice(&output);
+            }
+            received
+        });
+        t1.join().unwrap();
+        assert_eq!(t2.join().unwrap(), (0..=count).collect::<Vec<_>>());
+
+        // the method does the same
+        let (mut producer, mut consumer) = RingBuffer::<2>::new().split();
+        assert_eq!(producer.push(1), Ok(()));
+        assert_eq!(consumer.pop(), Some(1));
+    }
+
+    #[test]
+    fn blocking() {
+        let count = 1000;
+        let consumer = Arc::new(BlockingRingBuffer::<8>::new());
+        let producer = Arc::clone(&consumer);
+
+        // the consumer is waiting before the producer even starts
+        let t = thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut output = Vec::new();
+            while received.len() < count {
+                // every wakeup returns data, nothing is spun on
+                assert!(consumer.read_blocking(&mut output) > 0);
+                received.extend_from_slice(&output);
+            }
+            received
+        });
+        thread::sleep(std::time::Duration::from_millis(50));
+        for i in 0..count {
+            let data = vec![AtomicUsize::new(i)];
+            assert_eq!(producer.write_blocking(&data), 1);
+        }
+        assert_eq!(t.join().unwrap(), (0..count).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn read_timeout() {
+        let dur = Duration::from_millis(100);
+        let consumer = Arc::new(BlockingRingBuffer::<8>::new());
+        let mut output = Vec::new();
+
+        // nobody writes
+        let start = Instant::now();
+        assert_eq!(consumer.read_timeout(&mut output, dur), 0);
+        assert!(start.elapsed() >= dur);
+
+        // a producer writes within the window
+        let producer = Arc::clone(&consumer);
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            producer.write_blocking(&vec![AtomicUsize::new(7)]);
+        });
+        let start = Instant::now();
+        assert_eq!(consumer.read_timeout(&mut output, Duration::from_secs(5)), 1);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(output, vec![7]);
+        t.join().unwrap();
     }
 
     #[test]
@@ -210,18 +989,545 @@ mod test {
             for i in 0..10 {
                 data.push(AtomicUsize::new(i));
             }
-            loop {
+            for _ in 0..100000 {
                 let n =arc_ringbuffer1.n_write(&data);
                 println!("write data {}",n);
             }
         });
         thread::spawn(move || {
             let mut output = Vec::new();
-            loop {
+            for _ in 0..100000 {
                 let n = arc_ringbuffer2.n_read(&mut output);
                 println!("read data {}: {:?}",n, output);
             }
         });
         thread::sleep(std::time::Duration::from_millis(10000));
     }
+
+    #[test]
+    fn high_watermark() {
+        let ringbuffer = RingBuffer::<10>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.high_watermark(), 0);
+
+        ringbuffer.write_from(&[1, 2, 3]);
+        assert_eq!(ringbuffer.high_watermark(), 3);
+        ringbuffer.write_from(&[4, 5]);
+        assert_eq!(ringbuffer.high_watermark(), 5);
+
+        // draining and writing less doesn't lower it
+        ringbuffer.n_read(&mut result);
+        ringbuffer.write_from(&[6, 7]);
+        assert_eq!(ringbuffer.high_watermark(), 5);
+
+        // single pushes count too, and a short write stops at the capacity
+        ringbuffer.try_push(8).unwrap();
+        ringbuffer.write_from(&[0; 20]);
+        assert_eq!(ringbuffer.high_watermark(), 10);
+
+        // reset starts from the current occupancy
+        ringbuffer.n_read_upto(&mut result, 6);
+        ringbuffer.reset_high_watermark();
+        assert_eq!(ringbuffer.high_watermark(), 4);
+        ringbuffer.n_read(&mut result);
+        ringbuffer.reset_high_watermark();
+        assert_eq!(ringbuffer.high_watermark(), 0);
+    }
+
+    #[test]
+    fn write_overwrite() {
+        let ringbuffer = RingBuffer::<4>::new();
+        let mut result = Vec::new();
+
+        assert_eq!(ringbuffer.write_overwrite(&[1, 2, 3]), 0);
+        assert_eq!(ringbuffer.write_overwrite(&[4, 5, 6]), 2);
+        assert!(ringbuffer.is_full());
+        assert_eq!(ringbuffer.n_read(&mut result), 4);
+        assert_eq!(result, vec![3, 4, 5, 6]);
+
+        // longer than the buffer, only the newest N are kept
+        assert_eq!(ringbuffer.write_overwrite(&[7, 8]), 0);
+        assert_eq!(ringbuffer.write_overwrite(&[9, 10, 11, 12, 13, 14]), 4);
+        assert_eq!(ringbuffer.check_invariants(), Ok(()));
+        assert_eq!(ringbuffer.try_pop(), Some(11));
+        assert_eq!(ringbuffer.n_read(&mut result), 3);
+        assert_eq!(result, vec![12, 13, 14]);
+        assert_eq!(ringbuffer.high_watermark(), 4);
+    }
+
+    #[test]
+    fn multi_thread_write_overwrite() {
+        let count = 100_000;
+        let consumer = Arc::new(RingBuffer::<8>::new());
+        let producer = Arc::clone(&consumer);
+
+        let t = thread::spawn(move || {
+            let mut lost = 0;
+            for i in 1..=count {
+                lost += producer.write_overwrite(&[i, i]);
+            }
+            lost
+        });
+
+        // samples may be skipped but never go back in time or get torn
+        let mut last = 0;
+        let mut output = Vec::new();
+        let mut received = 0;
+        while last < count {
+            if consumer.n_read_upto(&mut output, 3) == 0 {
+                thread::yield_now();
+            }
+            for &value in &output {
+                assert!(value >= last, "{} after {}", value, last);
+                last = value;
+            }
+            received += output.len();
+        }
+        let lost = t.join().unwrap();
+        assert_eq!(received + lost + consumer.n_read(&mut output), 2 * count);
+    }
+
+    #[test]
+    fn free() {
+        let ringbuffer = RingBuffer::<5>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.free(), 5);
+        ringbuffer.write_from(&[1, 2, 3]);
+        assert_eq!(ringbuffer.free(), 2);
+        ringbuffer.n_read_upto(&mut result, 2);
+        assert_eq!(ringbuffer.free(), 4);
+        ringbuffer.write_overwrite(&[4, 5, 6, 7, 8, 9]);
+        assert_eq!(ringbuffer.free(), 0);
+        ringbuffer.try_pop();
+        assert_eq!(ringbuffer.free(), 1);
+    }
+
+    #[test]
+    fn snapshot() {
+        let ringbuffer = RingBuffer::<8>::new();
+        ringbuffer.write_from(&[1, 2, 3]);
+        assert_eq!(ringbuffer.snapshot(), BufferStats { len: 3, capacity: 8, free: 5 });
+
+        let count = 10_000;
+        let consumer = Arc::new(ringbuffer);
+        let producer = Arc::clone(&consumer);
+        let monitor = Arc::clone(&consumer);
+        let done = Arc::new(AtomicBool::new(false));
+        let monitor_done = Arc::clone(&done);
+
+        let p = thread::spawn(move || {
+            for i in 0..count {
+                while producer.try_push(i).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        let m = thread::spawn(move || {
+            while !monitor_done.load(Ordering::Relaxed) {
+                let stats = monitor.snapshot();
+                assert_eq!(stats.capacity, 8);
+                assert_eq!(stats.len + stats.free, stats.capacity);
+                thread::yield_now();
+            }
+        });
+
+        let mut output = Vec::new();
+        let mut received = 0;
+        while received < count + 3 {
+            if consumer.n_read(&mut output) == 0 {
+                thread::yield_now();
+            }
+            received += output.len();
+        }
+        p.join().unwrap();
+        done.store(true, Ordering::Relaxed);
+        m.join().unwrap();
+        assert_eq!(consumer.snapshot(), BufferStats { len: 0, capacity: 8, free: 8 });
+    }
+
+    #[tokio::test]
+    async fn read_async() {
+        let consumer = Arc::new(RingBuffer::<4>::new());
+        let producer = Arc::clone(&consumer);
+
+        let reader = tokio::spawn(async move {
+            let mut output = Vec::new();
+            let read_count = consumer.read_async(&mut output).await;
+            (read_count, output)
+        });
+        // let the reader find the buffer empty and go to sleep
+        tokio::task::yield_now().await;
+        assert!(!reader.is_finished());
+        assert!(producer.reader_waker.registered.load(Ordering::Relaxed));
+
+        tokio::spawn(async move {
+            producer.write_from(&[1, 2]);
+        })
+        .await
+        .unwrap();
+        assert_eq!(reader.await.unwrap(), (2, vec![1, 2]));
+    }
+
+    #[test]
+    fn default() {
+        let ringbuffer: RingBuffer<4, AtomicU32> = Default::default();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.write_from(&[1, 2]), 2);
+        assert_eq!(ringbuffer.n_read(&mut result), 2);
+        assert_eq!(result, vec![1, 2]);
+    }
+
+    #[test]
+    fn static_buffer() {
+        static RINGBUFFER: RingBuffer<4> = RingBuffer::new();
+
+        thread::spawn(|| {
+            for i in 1..=3 {
+                RINGBUFFER.try_push(i).unwrap();
+            }
+        })
+        .join()
+        .unwrap();
+        let mut result = Vec::new();
+        assert_eq!(RINGBUFFER.n_read(&mut result), 3);
+        assert_eq!(result, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn read_into_slice() {
+        let ringbuffer = RingBuffer::<4>::new();
+        let mut out = [0; 3];
+        assert_eq!(ringbuffer.read_into_slice(&mut out), 0);
+        ringbuffer.write_from(&[1, 2]);
+        assert_eq!(ringbuffer.read_into_slice(&mut out), 2);
+        assert_eq!(out, [1, 2, 0]);
+
+        let count = 100_000;
+        let consumer = Arc::new(RingBuffer::<128>::new());
+        let producer = Arc::clone(&consumer);
+        let t = thread::spawn(move || {
+            let mut next = 0;
+            while next < count {
+                let data: Vec<usize> = (next..min(next + 50, count)).collect();
+                let written = producer.write_from(&data);
+                if written == 0 {
+                    thread::yield_now();
+                }
+                next += written;
+            }
+        });
+
+        let mut out = [0; 64];
+        let mut expected = 0;
+        while expected < count {
+            let read_count = consumer.read_into_slice(&mut out);
+            if read_count == 0 {
+                thread::yield_now();
+            }
+            for &value in &out[..read_count] {
+                assert_eq!(value, expected);
+                expected += 1;
+            }
+        }
+        t.join().unwrap();
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn new_shared() {
+        let ringbuffer = RingBuffer::<4>::new_shared();
+        let producer = Arc::clone(&ringbuffer);
+        let consumer = Arc::clone(&ringbuffer);
+
+        let p = thread::spawn(move || {
+            for i in 1..=100 {
+                while producer.try_push(i).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        let c = thread::spawn(move || {
+            let mut sum = 0;
+            for _ in 1..=100 {
+                loop {
+                    match consumer.try_pop() {
+                        Some(value) => break sum += value,
+                        None => thread::yield_now(),
+                    }
+                }
+            }
+            sum
+        });
+        p.join().unwrap();
+        assert_eq!(c.join().unwrap(), 5050);
+
+        let blocking = BlockingRingBuffer::<4>::new_shared();
+        let producer = Arc::clone(&blocking);
+        thread::spawn(move || producer.write_blocking(&vec![AtomicUsize::new(7)]));
+        let mut result = Vec::new();
+        assert_eq!(blocking.read_blocking(&mut result), 1);
+        assert_eq!(result, vec![7]);
+    }
+
+    #[test]
+    fn close() {
+        let consumer = BlockingRingBuffer::<8>::new_shared();
+        let producer = Arc::clone(&consumer);
+        assert!(!consumer.is_closed());
+
+        let t = thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut output = Vec::new();
+            while consumer.read_blocking(&mut output) > 0 {
+                received.extend_from_slice(&output);
+            }
+            // stays closed
+            assert_eq!(consumer.read_timeout(&mut output, Duration::from_secs(5)), 0);
+            received
+        });
+        for i in 0..3 {
+            producer.write_blocking(&vec![AtomicUsize::new(i)]);
+        }
+        thread::sleep(Duration::from_millis(50));
+        producer.close();
+        assert!(producer.is_closed());
+        assert_eq!(t.join().unwrap(), vec![0, 1, 2]);
+
+        // data written before closing is still handed out
+        let ringbuffer = RingBuffer::<4>::new();
+        let mut output = Vec::new();
+        ringbuffer.write_from(&[5]);
+        ringbuffer.close();
+        assert_eq!(ringbuffer.n_read(&mut output), 1);
+        assert_eq!(ringbuffer.n_read(&mut output), 0);
+        assert!(ringbuffer.is_closed());
+    }
+
+    #[tokio::test]
+    async fn read_async_closed() {
+        let consumer = RingBuffer::<4>::new_shared();
+        let producer = Arc::clone(&consumer);
+
+        let reader = tokio::spawn(async move {
+            let mut output = Vec::new();
+            consumer.read_async(&mut output).await
+        });
+        tokio::task::yield_now().await;
+        assert!(!reader.is_finished());
+        producer.close();
+        assert_eq!(reader.await.unwrap(), 0);
+    }
+
+    #[test]
+    fn read_chunk_release() {
+        let (mut producer, mut consumer) = RingBuffer::<8>::new().split();
+        let mut result = Vec::new();
+        let (front, back) = consumer.read_chunk();
+        assert!(front.is_empty() && back.is_empty());
+
+        for _ in 0..5 {
+            producer.push(0).unwrap();
+        }
+        consumer.read(&mut result);
+        for i in 1..=6 {
+            producer.push(i).unwrap();
+        }
+        let (front, back) = consumer.read_chunk();
+        assert_eq!(front, &[1, 2, 3]);
+        assert_eq!(back, &[4, 5, 6]);
+
+        // looking doesn't consume, releasing part of it leaves the rest and frees the slots
+        consumer.release(4);
+        let (front, back) = consumer.read_chunk();
+        assert_eq!(front, &[5, 6]);
+        assert!(back.is_empty());
+        for i in 7..=12 {
+            producer.push(i).unwrap();
+        }
+        assert_eq!(producer.push(13), Err(13));
+        consumer.release(8);
+        assert_eq!(consumer.pop(), None);
+
+        // the producer keeps writing into the free slots while a chunk is looked at
+        let (mut producer, mut consumer) = RingBuffer::<8>::new().split();
+        let count = 10_000;
+        let t = thread::spawn(move || {
+            for i in 0..count {
+                while producer.push(i).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < count {
+            let (front, back) = consumer.read_chunk();
+            let n = front.len() + back.len();
+            for value in front.iter().chain(back) {
+                assert_eq!(*value, expected);
+                expected += 1;
+            }
+            if n == 0 {
+                thread::yield_now();
+            }
+            consumer.release(n);
+        }
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn take_overrun() {
+        let ringbuffer = RingBuffer::<4>::new();
+        let mut output = Vec::new();
+        assert!(!ringbuffer.take_overrun());
+        ringbuffer.write_overwrite(&[1, 2, 3, 4]);
+        assert!(!ringbuffer.take_overrun());
+        ringbuffer.write_overwrite(&[5]);
+        assert!(ringbuffer.take_overrun());
+        assert!(!ringbuffer.take_overrun());
+
+        // several losing writes before the flag is taken are reported once, writes that
+        // lose nothing don't set it again
+        ringbuffer.write_overwrite(&[6, 7]);
+        ringbuffer.write_overwrite(&[8, 9, 10, 11, 12]);
+        ringbuffer.n_read(&mut output);
+        ringbuffer.write_overwrite(&[13]);
+        assert!(ringbuffer.take_overrun());
+        assert!(!ringbuffer.take_overrun());
+        ringbuffer.write_overwrite(&[14, 15]);
+        assert!(!ringbuffer.take_overrun());
+
+        // a producer outrunning the consumer in episodes, the consumer sees each episode
+        // exactly once and the flag stays clear until the next one
+        let consumer = RingBuffer::<4>::new_shared();
+        let producer = Arc::clone(&consumer);
+        let barrier = Arc::new(Barrier::new(2));
+        let producer_barrier = Arc::clone(&barrier);
+        let episodes = 100;
+        let t = thread::spawn(move || {
+            for i in 0..episodes {
+                let lost: usize = (0..3).map(|j| producer.write_overwrite(&[i, j, 0])).sum();
+                assert!(lost > 0);
+                producer_barrier.wait();
+                // the consumer checks the flag and empties the buffer
+                producer_barrier.wait();
+                assert_eq!(producer.write_overwrite(&[i]), 0);
+            }
+        });
+        for _ in 0..episodes {
+            barrier.wait();
+            assert!(consumer.take_overrun());
+            assert!(!consumer.take_overrun());
+            consumer.n_read(&mut output);
+            barrier.wait();
+        }
+        t.join().unwrap();
+        assert!(!consumer.take_overrun());
+    }
+
+    #[test]
+    fn errors() {
+        use crate::error::RingBufferError;
+
+        let ringbuffer = RingBuffer::<4, AtomicU32>::new();
+        let mut result = Vec::new();
+        assert_eq!(ringbuffer.read_one(), Err(RingBufferError::Empty));
+        assert_eq!(
+            ringbuffer.write_exact(&[1, 2, 3, 4, 5]),
+            Err(RingBufferError::CapacityExceeded { requested: 5, capacity: 4 })
+        );
+        assert_eq!(ringbuffer.write_exact(&[1, 2, 3]), Ok(()));
+        assert_eq!(ringbuffer.write_exact(&[4, 5]), Err(RingBufferError::Full));
+        assert_eq!(ringbuffer.read_one(), Ok(1));
+        assert_eq!(ringbuffer.write_exact(&[4, 5]), Ok(()));
+        ringbuffer.n_read(&mut result);
+        assert_eq!(result, vec![2, 3, 4, 5]);
+    }
+
+}
+
+/// run with `RUSTFLAGS="--cfg loom" cargo test --lib ringbuffer_ts::loom_test`
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::sync::Arc;
+    use loom::thread;
+    use super::{AtomicUsize, RingBuffer};
+
+    #[test]
+    fn spsc_handshake() {
+        loom::model(|| {
+            let consumer = Arc::new(RingBuffer::<2>::new());
+            let producer = consumer.clone();
+
+            let t = thread::spawn(move || {
+                for i in 1..=3 {
+                    let data = vec![AtomicUsize::new(i)];
+                    while producer.n_write(&data) == 0 {
+                        thread::yield_now();
+                    }
+                }
+            });
+
+            let mut received = Vec::new();
+            let mut output = Vec::new();
+            while received.len() < 3 {
+                if consumer.n_read(&mut output) == 0 {
+                    thread::yield_now();
+                }
+                received.extend_from_slice(&output);
+            }
+            t.join().unwrap();
+            assert_eq!(received, vec![1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn overwrite_while_reading() {
+        loom::model(|| {
+            let consumer = Arc::new(RingBuffer::<2>::new());
+            let producer = consumer.clone();
+
+            let t = thread::spawn(move || {
+                for i in 1..=3 {
+                    producer.write_overwrite(&[i]);
+                }
+            });
+
+            // whatever is read comes out in order and is never a stale slot, the newest
+            // sample always gets through
+            let mut received = Vec::new();
+            let mut output = Vec::new();
+            while received.last() != Some(&3) {
+                if consumer.n_read(&mut output) == 0 {
+                    thread::yield_now();
+                }
+                received.extend_from_slice(&output);
+            }
+            t.join().unwrap();
+            assert!(received.windows(2).all(|w| w[0] < w[1]), "{:?}", received);
+        });
+    }
+
+    #[test]
+    fn lapped_while_reading() {
+        loom::model(|| {
+            let consumer = Arc::new(RingBuffer::<1>::new());
+            let producer = consumer.clone();
+            consumer.write_from(&[1]);
+
+            // two overwrites move head by 2N, all the way round to where the consumer
+            // loaded it
+            let t = thread::spawn(move || producer.write_overwrite(&[2]) + producer.write_overwrite(&[3]));
+
+            let received = consumer.try_pop();
+            let evicted = t.join().unwrap();
+            let mut rest = Vec::new();
+            consumer.n_read(&mut rest);
+            // every value is either evicted, read or still buffered, in order, and the
+            // newest one is never lost
+            let seen: Vec<usize> = received.into_iter().chain(rest).collect();
+            assert!(seen.windows(2).all(|w| w[0] < w[1]), "{:?}", seen);
+            assert_eq!(seen.last(), Some(&3), "{:?}", seen);
+            assert_eq!(seen.len() + evicted, 3, "{:?}", seen);
+        });
+    }
 }
@@ -1,5 +1,6 @@
 pub struct Stack<T> {
     head: Node<T>,
+    len: usize,
 }
 
 type Node<T> = Option<Box<Content<T>>>;
@@ -10,31 +11,182 @@ struct Content<T> {
 }
 
 
-impl<T> Stack<T> 
-where
-    T: std::fmt::Debug,
-{
+impl<T> Stack<T> {
     pub fn new() -> Self {
-        Stack { head: None }
+        Stack { head: None, len: 0 }
     }
     pub fn push(&mut self, elem: T) {
         let new_node = Box::new(Content {
-            elem: elem,
+            elem,
             next: self.head.take(),
         });
         self.head = Some(new_node);
+        self.len += 1;
     }
     pub fn pop(&mut self) -> Option<T> {
         self.head.take().map(|node| {
             self.head = node.next;
+            self.len -= 1;
             node.elem
         })
     }
-    // pub fn peek(&self) -> Option<&T> {
-    //     self.head.map(|node| {
-    //         &node.elem
-    //     })
-    // }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// `as_ref` borrows the node instead of moving it out of `self.head`
+    pub fn peek(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.elem)
+    }
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.head.as_mut().map(|node| &mut node.elem)
+    }
+    /// borrow the elements from top to bottom
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { next: self.head.as_deref() }
+    }
+    /// mutably borrow the elements from top to bottom
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut { next: self.head.as_deref_mut() }
+    }
+    /// remove the bottom element, walks the whole list
+    fn pop_bottom(&mut self) -> Option<T> {
+        let mut link = &mut self.head;
+        while link.as_ref()?.next.is_some() {
+            link = &mut link.as_mut()?.next;
+        }
+        let node = link.take()?;
+        self.len -= 1;
+        Some(node.elem)
+    }
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// a `Stack` holding at most `N` elements, pushing onto a full one drops the bottom
+/// (oldest) element to make room. Eviction walks the list, so a push is O(N) once full.
+pub struct BoundedStack<T, const N: usize> {
+    stack: Stack<T>,
+}
+
+impl<T, const N: usize> BoundedStack<T, N> {
+    /// a capacity of 0 fails to compile, a push would evict the element it just pushed
+    const NON_ZERO: () = assert!(N > 0, "capacity of BoundedStack must be non-zero");
+
+    pub fn new() -> Self {
+        let () = Self::NON_ZERO;
+        BoundedStack { stack: Stack::new() }
+    }
+    /// push `elem` on top, dropping the bottom element when the stack is full
+    pub fn push(&mut self, elem: T) {
+        if self.stack.len() == N {
+            self.stack.pop_bottom();
+        }
+        self.stack.push(elem);
+    }
+    pub fn pop(&mut self) -> Option<T> {
+        self.stack.pop()
+    }
+    pub fn peek(&self) -> Option<&T> {
+        self.stack.peek()
+    }
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+    pub fn is_full(&self) -> bool {
+        self.stack.len() == N
+    }
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    /// borrow the elements from top to bottom
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.stack.iter()
+    }
+}
+
+impl<T, const N: usize> Default for BoundedStack<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a, T> {
+    next: Option<&'a Content<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            &node.elem
+        })
+    }
+}
+
+pub struct IterMut<'a, T> {
+    next: Option<&'a mut Content<T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    /// `take` because a `&mut` can't be copied out of `self.next` like `Iter` does
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.take().map(|node| {
+            self.next = node.next.as_deref_mut();
+            &mut node.elem
+        })
+    }
+}
+
+/// pops the elements off from top to bottom
+pub struct IntoIter<T>(Stack<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+}
+
+impl<T> IntoIterator for Stack<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+/// elements are pushed in iteration order, so the last one consumed ends up on top
+impl<T> FromIterator<T> for Stack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stack = Stack::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+/// elements are pushed on top in iteration order, so the last one consumed ends up on top
+impl<T> Extend<T> for Stack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push(elem);
+        }
+    }
 }
 
 /// 由于Box<Content>的drop trait不是尾递归(如果是函数操作作为drop结尾就是尾递归,如果结尾是别的操作,
@@ -52,30 +204,30 @@ impl<T> Drop for Stack<T> {
 
 #[cfg(test)]
 mod test {
-    use super::Stack;
+    use super::{BoundedStack, Stack};
 
     #[test]
     fn basics() {
-        let mut Stack = Stack::new();
+        let mut stack = Stack::new();
 
         // Check empty Stack behaves right
-        assert_eq!(Stack.pop(), None);
+        assert_eq!(stack.pop(), None);
         // Populate Stack
-        Stack.push(1);
-        Stack.push(2);
-        Stack.push(3);
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
         // Check normal removal
-        assert_eq!(Stack.pop(), Some(3));
-        assert_eq!(Stack.pop(), Some(2));
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
         // Push some more just to make sure nothing's corrupted
-        Stack.push(4);
-        Stack.push(5);
+        stack.push(4);
+        stack.push(5);
         // Check normal removal
-        assert_eq!(Stack.pop(), Some(5));
-        assert_eq!(Stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(5));
+        assert_eq!(stack.pop(), Some(4));
         // Check exhaustion
-        assert_eq!(Stack.pop(), Some(1));
-        assert_eq!(Stack.pop(), None);
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
     }
 
     /// 使用情况:1.当我们只有一个变量的可变引用的时候,我们想获得这个变量的所有权;2.我们的变量没有实
@@ -109,4 +261,141 @@ mod test {
         }
         drop(stack);
     }
+
+    #[test]
+    fn peek() {
+        let mut stack = Stack::new();
+        assert_eq!(stack.peek(), None);
+        assert_eq!(stack.peek_mut(), None);
+
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.peek(), Some(&2));
+        assert_eq!(stack.peek_mut(), Some(&mut 2));
+
+        if let Some(value) = stack.peek_mut() {
+            *value = 42;
+        }
+        assert_eq!(stack.peek(), Some(&42));
+        assert_eq!(stack.pop(), Some(42));
+        assert_eq!(stack.peek(), Some(&1));
+    }
+
+    #[test]
+    fn len() {
+        let mut stack = Stack::new();
+        assert_eq!(stack.len(), 0);
+        assert!(stack.is_empty());
+
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+        assert_eq!(stack.len(), 3);
+        stack.pop();
+        assert_eq!(stack.len(), 2);
+        assert!(!stack.is_empty());
+        stack.pop();
+        stack.pop();
+        assert_eq!(stack.len(), 0);
+        assert!(stack.is_empty());
+
+        // popping an empty stack leaves it empty
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.len(), 0);
+    }
+
+    #[test]
+    fn iter() {
+        let mut stack = Stack::new();
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+
+        assert_eq!(stack.iter().collect::<Vec<_>>(), vec![&3, &2, &1]);
+
+        for value in stack.iter_mut() {
+            *value *= 10;
+        }
+        assert_eq!(stack.iter().collect::<Vec<_>>(), vec![&30, &20, &10]);
+        // borrowing doesn't remove anything
+        assert_eq!(stack.len(), 3);
+
+        assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![30, 20, 10]);
+
+        let mut empty = Stack::<i32>::new();
+        assert_eq!(empty.iter().next(), None);
+        assert_eq!(empty.iter_mut().next(), None);
+        assert_eq!(empty.into_iter().next(), None);
+    }
+
+    #[test]
+    fn collect_extend() {
+        let mut stack: Stack<i32> = (1..=3).collect();
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.peek(), Some(&3));
+
+        stack.extend(vec![4, 5]);
+        assert_eq!(stack.len(), 5);
+        assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn non_debug_elements() {
+        // deliberately doesn't derive Debug
+        struct Opaque(u32);
+
+        let mut stack = Stack::new();
+        stack.push(Opaque(1));
+        stack.push(Opaque(2));
+        assert_eq!(stack.pop().map(|o| o.0), Some(2));
+        assert_eq!(stack.pop().map(|o| o.0), Some(1));
+        assert!(stack.pop().is_none());
+    }
+
+    #[test]
+    fn bounded() {
+        let mut stack = BoundedStack::<i32, 3>::new();
+        assert_eq!(stack.capacity(), 3);
+        assert_eq!(stack.pop(), None);
+
+        for i in 1..=5 {
+            stack.push(i);
+        }
+        // 1 and 2 were at the bottom
+        assert!(stack.is_full());
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.iter().collect::<Vec<_>>(), vec![&5, &4, &3]);
+        assert_eq!(stack.peek(), Some(&5));
+
+        assert_eq!(stack.pop(), Some(5));
+        stack.push(6);
+        assert_eq!(stack.len(), 3);
+        stack.push(7);
+        assert_eq!(stack.iter().collect::<Vec<_>>(), vec![&7, &6, &4]);
+        assert_eq!(stack.pop(), Some(7));
+        assert_eq!(stack.pop(), Some(6));
+        assert_eq!(stack.pop(), Some(4));
+        assert!(stack.is_empty());
+
+        let mut one = BoundedStack::<i32, 1>::new();
+        one.push(1);
+        one.push(2);
+        assert_eq!(one.pop(), Some(2));
+        assert_eq!(one.pop(), None);
+    }
+
+    #[test]
+    fn bounded_drops_evicted() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        let mut stack = BoundedStack::<Rc<()>, 2>::new();
+        for _ in 0..5 {
+            stack.push(Rc::clone(&value));
+        }
+        assert_eq!(Rc::strong_count(&value), 3);
+        drop(stack);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
 }
@@ -0,0 +1,16 @@
+#![cfg(feature = "std")]
+
+#[test]
+fn zero_capacity() {
+    let t = trybuild::TestCases::new();
+    // the capacity check only fires once `new` is monomorphized, having a `pass` case makes
+    // trybuild build the tests instead of just checking them
+    t.pass("tests/ui/nonzero_capacity.rs");
+    t.compile_fail("tests/ui/zero_capacity_*.rs");
+}
+
+#[test]
+fn single_consumer() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/shared_consumer_*.rs");
+}
@@ -0,0 +1,15 @@
+#![cfg(feature = "alloc")]
+
+use ringbuffer::ringbuffer::RingBuffer;
+
+#[test]
+fn write_read_from_outside() {
+    let mut ringbuffer = RingBuffer::<u32, 4>::new();
+    let data = vec![1, 2, 3, 4, 5];
+    let mut result = Vec::new();
+
+    assert_eq!(ringbuffer.n_write(&data), 4);
+    assert_eq!(ringbuffer.n_read(&mut result), 4);
+    assert_eq!(result, vec![1, 2, 3, 4]);
+    assert_eq!(ringbuffer.n_read(&mut result), 0);
+}
@@ -0,0 +1,5 @@
+fn main() {
+    let _ringbuffer = ringbuffer::ringbuffer::RingBuffer::<u8, 1>::new();
+    let _ringbuffer_ts = ringbuffer::ringbuffer_ts::RingBuffer::<1>::new();
+    let _ringbuffer_ts_g = ringbuffer::ringbuffer_ts_g::RingBuffer::<u8, 1>::new();
+}
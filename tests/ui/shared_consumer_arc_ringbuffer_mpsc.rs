@@ -0,0 +1,8 @@
+use std::sync::Arc;
+use ringbuffer::ringbuffer_mpsc::MpscRingBuffer;
+
+fn main() {
+    // only the one consumer can pop, not everyone holding the buffer
+    let ringbuffer = Arc::new(MpscRingBuffer::<4>::new());
+    ringbuffer.pop();
+}
@@ -0,0 +1,6 @@
+use ringbuffer::ringbuffer_mpsc::MpscRingBuffer;
+
+fn main() {
+    let (_producer, consumer) = MpscRingBuffer::<4>::new().split();
+    let _second = consumer.clone();
+}
@@ -0,0 +1,5 @@
+use ringbuffer::ringbuffer::RingBuffer;
+
+fn main() {
+    let _ringbuffer = RingBuffer::<u8, 0>::new();
+}
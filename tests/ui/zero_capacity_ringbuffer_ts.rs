@@ -0,0 +1,5 @@
+use ringbuffer::ringbuffer_ts::RingBuffer;
+
+fn main() {
+    let _ringbuffer = RingBuffer::<0>::new();
+}